@@ -1,11 +1,16 @@
 use crate::app::ffmpeg_manager::{
-    AudioCodec, FfmpegManager, PixelFormat, SubtitleCodec, VideoCodec,
+    AudioCodec, Chapter, ChapterMapping, ChapterSplit, Container, DISPOSITION_FLAGS,
+    ExistingOutput, FfmpegManager, LANGUAGES, Manifest, ManifestEntry, OutputLocation, OutputState,
+    PixelFormat, ReplaceOriginal, ScanSettings, SkipRules, StreamRules, SubtitleCodec, VideoCodec,
+    clear_probe_cache, copy_attributes, get_metadata_path, get_partial_path, import_chapters,
+    load_preset, normalize_chapters, parse_timestamp, save_preset,
 };
 use crate::app::hotkey::HotKey;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers};
-use ratatui::layout::Constraint::{Fill, Length, Min};
+use ratatui::layout::Constraint::{Fill, Length, Min, Percentage};
+use ratatui::layout::Flex;
 use ratatui::style::Stylize;
-use ratatui::widgets::{List, ListItem, ListState, Paragraph, StatefulWidget, Wrap};
+use ratatui::widgets::{Clear, List, ListItem, ListState, Paragraph, StatefulWidget, Wrap};
 use ratatui::{
     DefaultTerminal, Frame,
     buffer::Buffer,
@@ -15,12 +20,24 @@ use ratatui::{
     text::Line,
     widgets::{Block, Widget},
 };
-use std::fs::create_dir;
-use std::path::PathBuf;
+use std::fs::{create_dir_all, remove_file, rename, write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
 use std::{env, io};
 use strum::IntoEnumIterator;
 
+enum PresetEdit {
+    Save,
+    Load,
+}
+
+enum ChapterEdit {
+    Rename,
+    Add,
+    Import,
+}
+
 pub struct App {
     exit: bool,
     hotkeys: Vec<HotKey>,
@@ -29,6 +46,19 @@ pub struct App {
     selected_compress_setting: ListState,
     editing_string: Option<String>,
     cursor_position: (u16, u16),
+    editing_chapters: bool,
+    selected_chapter: ListState,
+    chapter_edit: Option<ChapterEdit>,
+    showing_file_streams: bool,
+    selected_file_stream: ListState,
+    preset_edit: Option<PresetEdit>,
+    editing_title: bool,
+    selected_language: ListState,
+    selected_disposition: ListState,
+    showing_rejected_file: bool,
+    error_message: Option<String>,
+    /// Outputs of the finished batch, shown until exit
+    results: Option<Manifest>,
 }
 
 impl App {
@@ -41,15 +71,44 @@ impl App {
             selected_compress_setting: ListState::default(),
             editing_string: None,
             cursor_position: (0, 0),
+            editing_chapters: false,
+            selected_chapter: ListState::default(),
+            chapter_edit: None,
+            showing_file_streams: false,
+            selected_file_stream: ListState::default(),
+            preset_edit: None,
+            editing_title: false,
+            selected_language: ListState::default(),
+            selected_disposition: ListState::default(),
+            showing_rejected_file: false,
+            error_message: None,
+            results: None,
         };
         new_app.update_hotkeys();
-        let args = env::args().collect::<Vec<_>>();
-        match args.len() {
-            1 => new_app
+        let mut scan_settings = ScanSettings::load();
+        let mut paths = Vec::new();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--clear-cache" => {
+                    let _ = clear_probe_cache();
+                }
+                _ if scan_settings.parse_arg(&arg, &mut args) => {}
+                _ => paths.push(PathBuf::from(arg)),
+            }
+        }
+        new_app.ffmpeg_manager.scan_settings = scan_settings;
+        new_app.ffmpeg_manager.manifest = Manifest::load();
+        // Before the scan, so partial files are not added as inputs
+        if let Some(manifest) = &new_app.ffmpeg_manager.manifest {
+            manifest.remove_partials();
+        }
+        match paths.len() {
+            0 => new_app
                 .ffmpeg_manager
                 .add_folder(env::current_dir().unwrap()),
-            _ => args[1..].iter().for_each(|arg| {
-                new_app.ffmpeg_manager.add_path(PathBuf::from(arg));
+            _ => paths.into_iter().for_each(|path| {
+                new_app.ffmpeg_manager.add_path(path);
             }),
         }
         new_app
@@ -73,6 +132,30 @@ impl App {
     }
 
     fn update_hotkeys(&mut self) {
+        if self.results.is_some() {
+            self.hotkeys = vec![HotKey {
+                text: "Exit".to_string(),
+                key_event: KeyEvent {
+                    code: KeyCode::Esc,
+                    modifiers: KeyModifiers::empty(),
+                    kind: KeyEventKind::Press,
+                    state: KeyEventState::empty(),
+                },
+            }];
+            return;
+        }
+        if self.error_message.is_some() {
+            self.hotkeys = vec![HotKey {
+                text: "Close".to_string(),
+                key_event: KeyEvent {
+                    code: KeyCode::Esc,
+                    modifiers: KeyModifiers::empty(),
+                    kind: KeyEventKind::Press,
+                    state: KeyEventState::empty(),
+                },
+            }];
+            return;
+        }
         let mut result = vec![
             HotKey {
                 text: "Run".to_string(),
@@ -83,6 +166,24 @@ impl App {
                     state: KeyEventState::empty(),
                 },
             },
+            HotKey {
+                text: "Save preset".to_string(),
+                key_event: KeyEvent {
+                    code: KeyCode::Char('s'),
+                    modifiers: KeyModifiers::CONTROL,
+                    kind: KeyEventKind::Press,
+                    state: KeyEventState::empty(),
+                },
+            },
+            HotKey {
+                text: "Load preset".to_string(),
+                key_event: KeyEvent {
+                    code: KeyCode::Char('l'),
+                    modifiers: KeyModifiers::CONTROL,
+                    kind: KeyEventKind::Press,
+                    state: KeyEventState::empty(),
+                },
+            },
             HotKey {
                 text: "Close app".to_string(),
                 key_event: KeyEvent {
@@ -95,6 +196,46 @@ impl App {
         ];
         if let Some(selection) = self.get_selected() {
             match selection {
+                0 if self.selected_disposition.selected().is_some() => {
+                    result.push(HotKey {
+                        text: "Toggle flag".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Enter,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Exit".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Esc,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                }
+                0 if self.selected_language.selected().is_some() => {
+                    result.push(HotKey {
+                        text: "Select".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Enter,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Exit".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Esc,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                }
                 0 => {
                     result.push(HotKey {
                         text: "Toggle enabled".to_string(),
@@ -114,6 +255,51 @@ impl App {
                             state: KeyEventState::empty(),
                         },
                     });
+                    result.push(HotKey {
+                        text: "Move up".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::PageUp,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Move down".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::PageDown,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Edit title".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Char('t'),
+                            modifiers: KeyModifiers::CONTROL,
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Edit language".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Char('g'),
+                            modifiers: KeyModifiers::CONTROL,
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Edit flags".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Char('f'),
+                            modifiers: KeyModifiers::CONTROL,
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
                 }
                 1 => {
                     result.push(HotKey {
@@ -139,15 +325,169 @@ impl App {
                         })
                     }
                 }
-                2 => result.push(HotKey {
-                    text: "Delete file from list".to_string(),
-                    key_event: KeyEvent {
-                        code: KeyCode::Delete,
-                        modifiers: KeyModifiers::empty(),
-                        kind: KeyEventKind::Press,
-                        state: KeyEventState::empty(),
-                    },
-                }),
+                2 if self.showing_file_streams => {
+                    result.push(HotKey {
+                        text: "Toggle enabled".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Enter,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Toggle default".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Char('d'),
+                            modifiers: KeyModifiers::CONTROL,
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Reset override".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Backspace,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Exit".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Esc,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                }
+                2 if self.editing_chapters => {
+                    result.push(HotKey {
+                        text: "Rename".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Enter,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Delete chapter".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Delete,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Add chapter".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Char('a'),
+                            modifiers: KeyModifiers::CONTROL,
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Import chapters".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Char('o'),
+                            modifiers: KeyModifiers::CONTROL,
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Exit".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Esc,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                }
+                2 if self.showing_rejected_file => {
+                    result.push(HotKey {
+                        text: "Retry probe".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Enter,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Exit".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Esc,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                }
+                2 if self.get_selected_rejected_file().is_some() => {
+                    result.push(HotKey {
+                        text: "Details".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Enter,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Delete file from list".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Delete,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                }
+                2 => {
+                    result.push(HotKey {
+                        text: "File streams".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Enter,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Delete file from list".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Delete,
+                            modifiers: KeyModifiers::empty(),
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Edit chapters".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Char('e'),
+                            modifiers: KeyModifiers::CONTROL,
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                    result.push(HotKey {
+                        text: "Toggle skip".to_string(),
+                        key_event: KeyEvent {
+                            code: KeyCode::Char('k'),
+                            modifiers: KeyModifiers::CONTROL,
+                            kind: KeyEventKind::Press,
+                            state: KeyEventState::empty(),
+                        },
+                    });
+                }
                 _ => unreachable!(),
             }
         }
@@ -156,15 +496,62 @@ impl App {
 
     /// Returns index of selected list (0/1/2) or None
     pub fn get_selected(&self) -> Option<usize> {
-        for i in 0..3 {
-            if self.selections[i].selected().is_some() {
-                return Some(i);
-            }
+        (0..3).find(|&i| self.selections[i].selected().is_some())
+    }
+
+    /// Returns index of the rejected file selected in the Files list.
+    /// Rejected files are listed after the input files.
+    fn get_selected_rejected_file(&self) -> Option<usize> {
+        self.selections[2]
+            .selected()?
+            .checked_sub(self.ffmpeg_manager.input_files.len())
+            .filter(|&i| i < self.ffmpeg_manager.rejected_files.len())
+    }
+
+    /// Returns path of the input or rejected file at the index of the Files list
+    fn get_file_path(&self, index: usize) -> Option<PathBuf> {
+        let input_files = &self.ffmpeg_manager.input_files;
+        match input_files.get(index) {
+            Some(input_file) => Some(input_file.path.clone()),
+            None => self
+                .ffmpeg_manager
+                .rejected_files
+                .get(index - input_files.len())
+                .map(|rejected_file| rejected_file.path.clone()),
         }
-        None
+    }
+
+    /// Returns index of the input or rejected file in the Files list
+    fn get_file_index(&self, path: &Path) -> Option<usize> {
+        let input_files = &self.ffmpeg_manager.input_files;
+        input_files
+            .iter()
+            .position(|input_file| input_file.path == path)
+            .or_else(|| {
+                self.ffmpeg_manager
+                    .rejected_files
+                    .iter()
+                    .position(|rejected_file| rejected_file.path == path)
+                    .map(|i| input_files.len() + i)
+            })
     }
 
     fn handle_events(&mut self) -> io::Result<()> {
+        // Probed files are added between key events, so the list is updated while probing.
+        // They are inserted by path, so the selection follows the selected file and popups
+        // opened on it keep their file.
+        let selected_path = self.selections[2]
+            .selected()
+            .and_then(|selected| self.get_file_path(selected));
+        if self.ffmpeg_manager.receive_probe_results() {
+            if let Some(index) = selected_path.and_then(|path| self.get_file_index(&path)) {
+                self.selections[2].select(Some(index));
+            }
+            self.update_hotkeys();
+        }
+        if !event::poll(Duration::from_millis(100))? {
+            return Ok(());
+        }
         match event::read()? {
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
                 self.handle_key_event(key_event);
@@ -175,6 +562,19 @@ impl App {
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if self.results.is_some() {
+            if matches!(key_event.code, KeyCode::Esc | KeyCode::Enter) {
+                self.exit = true;
+            }
+            return;
+        }
+        if self.error_message.is_some() {
+            if matches!(key_event.code, KeyCode::Esc | KeyCode::Enter) {
+                self.error_message = None;
+                self.update_hotkeys();
+            }
+            return;
+        }
         if key_event.modifiers.contains(KeyModifiers::CONTROL) {
             match key_event.code {
                 KeyCode::Char('r') => self.run_compressing(),
@@ -183,6 +583,87 @@ impl App {
                     self.ffmpeg_manager.stream_settings[self.selections[0].selected().unwrap()]
                         .toggle_default();
                 }
+                KeyCode::Char('k')
+                    if self.selections[2].selected().is_some_and(|selected| {
+                        selected < self.ffmpeg_manager.input_files.len()
+                    }) && self.editing_string.is_none()
+                        && !self.showing_file_streams
+                        && !self.editing_chapters =>
+                {
+                    self.ffmpeg_manager
+                        .toggle_skip(self.selections[2].selected().unwrap());
+                }
+                KeyCode::Char('e')
+                    if self.selections[2].selected().is_some()
+                        && self.editing_string.is_none()
+                        && !self.showing_file_streams =>
+                {
+                    self.editing_chapters = true;
+                    self.selected_chapter.select_first();
+                    self.update_hotkeys();
+                    return;
+                }
+                KeyCode::Char('t')
+                    if self.selections[0].selected().is_some() && self.editing_string.is_none() =>
+                {
+                    let stream_settings = &self.ffmpeg_manager.stream_settings
+                        [self.selections[0].selected().unwrap()];
+                    self.editing_title = true;
+                    self.editing_string = Some(
+                        stream_settings
+                            .title
+                            .clone()
+                            .or(stream_settings.stream.get_title().cloned())
+                            .unwrap_or_default(),
+                    );
+                    self.update_hotkeys();
+                    return;
+                }
+                KeyCode::Char('g')
+                    if self.selections[0].selected().is_some() && self.editing_string.is_none() =>
+                {
+                    let stream_settings = &self.ffmpeg_manager.stream_settings
+                        [self.selections[0].selected().unwrap()];
+                    let language = stream_settings
+                        .language
+                        .as_ref()
+                        .or(stream_settings.stream.get_language());
+                    self.selected_language.select(Some(
+                        LANGUAGES
+                            .iter()
+                            .position(|(code, _, _)| Some(&code.to_string()) == language)
+                            .map(|i| i + 1)
+                            .unwrap_or(0),
+                    ));
+                    self.update_hotkeys();
+                    return;
+                }
+                KeyCode::Char('f')
+                    if self.selections[0].selected().is_some() && self.editing_string.is_none() =>
+                {
+                    self.selected_language.select(None);
+                    self.selected_disposition.select_first();
+                    self.update_hotkeys();
+                    return;
+                }
+                KeyCode::Char('s')
+                    if self.editing_string.is_none()
+                        && self.selected_compress_setting.selected().is_none() =>
+                {
+                    self.preset_edit = Some(PresetEdit::Save);
+                    self.editing_string = Some(String::new());
+                    self.update_hotkeys();
+                    return;
+                }
+                KeyCode::Char('l')
+                    if self.editing_string.is_none()
+                        && self.selected_compress_setting.selected().is_none() =>
+                {
+                    self.preset_edit = Some(PresetEdit::Load);
+                    self.editing_string = Some(String::new());
+                    self.update_hotkeys();
+                    return;
+                }
                 _ => {}
             }
         }
@@ -201,17 +682,49 @@ impl App {
                             self.ffmpeg_manager.compress_settings.video_codec =
                                 VideoCodec::iter().nth(selected_compress_setting).unwrap();
                         }
-                        1 => {
-                            self.ffmpeg_manager.compress_settings.pixel_format =
-                                PixelFormat::iter().nth(selected_compress_setting).unwrap();
+                        1 => {
+                            self.ffmpeg_manager.compress_settings.pixel_format =
+                                PixelFormat::iter().nth(selected_compress_setting).unwrap();
+                        }
+                        2 => {
+                            self.ffmpeg_manager.compress_settings.audio_codec =
+                                AudioCodec::iter().nth(selected_compress_setting).unwrap();
+                        }
+                        3 => {
+                            self.ffmpeg_manager.compress_settings.subtitle_codec =
+                                SubtitleCodec::iter()
+                                    .nth(selected_compress_setting)
+                                    .unwrap()
+                        }
+                        9 => {
+                            self.ffmpeg_manager.compress_settings.chapter_split =
+                                ChapterSplit::iter().nth(selected_compress_setting).unwrap()
+                        }
+                        11 => {
+                            self.ffmpeg_manager.compress_settings.chapter_mapping =
+                                ChapterMapping::iter()
+                                    .nth(selected_compress_setting)
+                                    .unwrap()
+                        }
+                        14 => {
+                            self.ffmpeg_manager.compress_settings.container =
+                                Container::iter().nth(selected_compress_setting).unwrap()
+                        }
+                        16 => {
+                            self.ffmpeg_manager.compress_settings.output_location =
+                                OutputLocation::iter()
+                                    .nth(selected_compress_setting)
+                                    .unwrap()
                         }
-                        2 => {
-                            self.ffmpeg_manager.compress_settings.audio_codec =
-                                AudioCodec::iter().nth(selected_compress_setting).unwrap();
+                        18 => {
+                            self.ffmpeg_manager.compress_settings.existing_output =
+                                ExistingOutput::iter()
+                                    .nth(selected_compress_setting)
+                                    .unwrap()
                         }
-                        3 => {
-                            self.ffmpeg_manager.compress_settings.subtitle_codec =
-                                SubtitleCodec::iter()
+                        19 => {
+                            self.ffmpeg_manager.compress_settings.replace_original =
+                                ReplaceOriginal::iter()
                                     .nth(selected_compress_setting)
                                     .unwrap()
                         }
@@ -224,7 +737,50 @@ impl App {
         } else if self.editing_string.is_some() {
             // bitrate|crop|scale|other input
             match key_event.code {
-                KeyCode::Esc => self.editing_string = None,
+                KeyCode::Esc => {
+                    self.editing_string = None;
+                    self.chapter_edit = None;
+                    self.preset_edit = None;
+                    self.editing_title = false;
+                }
+                KeyCode::Enter if self.editing_title => {
+                    self.editing_title = false;
+                    let title = self.editing_string.take().unwrap();
+                    self.ffmpeg_manager
+                        .set_title(self.selections[0].selected().unwrap(), title);
+                }
+                KeyCode::Enter if self.preset_edit.is_some() => {
+                    let preset_edit = self.preset_edit.take().unwrap();
+                    let name = self.editing_string.take().unwrap();
+                    let name = name.trim();
+                    if !name.is_empty() {
+                        match preset_edit {
+                            PresetEdit::Save => {
+                                if let Err(error) =
+                                    save_preset(name, &self.ffmpeg_manager.compress_settings)
+                                {
+                                    self.error_message =
+                                        Some(format!("Preset {} is not saved: {}", name, error));
+                                }
+                            }
+                            PresetEdit::Load => match load_preset(name) {
+                                Ok(compress_settings) => {
+                                    self.ffmpeg_manager.compress_settings = compress_settings;
+                                    self.ffmpeg_manager.apply_stream_rules();
+                                }
+                                Err(error) => {
+                                    self.error_message =
+                                        Some(format!("Preset {} is not loaded: {}", name, error));
+                                }
+                            },
+                        }
+                    }
+                }
+                KeyCode::Enter if self.chapter_edit.is_some() => {
+                    let chapter_edit = self.chapter_edit.take().unwrap();
+                    let editing_string = self.editing_string.take().unwrap();
+                    self.apply_chapter_edit(chapter_edit, editing_string);
+                }
                 KeyCode::Enter => {
                     let selection = self.selections[1].selected().unwrap();
                     let editing_string = self.editing_string.take().unwrap();
@@ -240,7 +796,25 @@ impl App {
                         7 => self.ffmpeg_manager.compress_settings.scale = new_value,
                         8 => {
                             self.ffmpeg_manager.compress_settings.other_settings =
-                                new_value.unwrap_or(String::new())
+                                new_value.unwrap_or_default()
+                        }
+                        10 => self.ffmpeg_manager.compress_settings.chapter_ranges = new_value,
+                        12 => match StreamRules::parse(&new_value.unwrap_or_default()) {
+                            Ok(stream_rules) => {
+                                self.ffmpeg_manager.compress_settings.stream_rules = stream_rules;
+                                self.ffmpeg_manager.apply_stream_rules();
+                            }
+                            Err(error) => self.error_message = Some(error),
+                        },
+                        13 => self.ffmpeg_manager.compress_settings.cover = new_value,
+                        15 => self.ffmpeg_manager.compress_settings.output_folder = new_value,
+                        17 => self.ffmpeg_manager.compress_settings.output_template = new_value,
+                        20 => self.ffmpeg_manager.compress_settings.min_saving = new_value,
+                        21 => self.ffmpeg_manager.compress_settings.trash_folder = new_value,
+                        22 => self.ffmpeg_manager.compress_settings.backup_suffix = new_value,
+                        24 => {
+                            self.ffmpeg_manager.compress_settings.skip_rules =
+                                SkipRules::parse(&new_value.unwrap_or_default())
                         }
                         _ => unreachable!(),
                     }
@@ -254,6 +828,53 @@ impl App {
                 }
                 _ => {}
             }
+        } else if self.selected_disposition.selected().is_some() {
+            // Disposition flags list
+            match key_event.code {
+                KeyCode::Esc => self.selected_disposition.select(None),
+                KeyCode::Up => self.selected_disposition.select_previous(),
+                KeyCode::Down => self.selected_disposition.select_next(),
+                KeyCode::Enter => {
+                    self.ffmpeg_manager.stream_settings[self.selections[0].selected().unwrap()]
+                        .toggle_disposition(self.selected_disposition.selected().unwrap());
+                }
+                _ => {}
+            }
+        } else if self.selected_language.selected().is_some() {
+            // Language comboBox
+            match key_event.code {
+                KeyCode::Esc => self.selected_language.select(None),
+                KeyCode::Up => self.selected_language.select_previous(),
+                KeyCode::Down => self.selected_language.select_next(),
+                KeyCode::Enter => {
+                    let language = self
+                        .selected_language
+                        .selected()
+                        .unwrap()
+                        .checked_sub(1)
+                        .and_then(|i| LANGUAGES.get(i))
+                        .map(|(code, _, _)| code.to_string());
+                    self.ffmpeg_manager
+                        .set_language(self.selections[0].selected().unwrap(), language);
+                    self.selected_language.select(None);
+                }
+                _ => {}
+            }
+        } else if self.showing_rejected_file {
+            match key_event.code {
+                KeyCode::Esc => self.showing_rejected_file = false,
+                KeyCode::Enter => {
+                    if let Some(rejected_file) = self.get_selected_rejected_file() {
+                        self.ffmpeg_manager.retry_rejected_file(rejected_file);
+                    }
+                    self.showing_rejected_file = false;
+                }
+                _ => {}
+            }
+        } else if self.editing_chapters {
+            self.handle_chapter_editor_key_event(key_event);
+        } else if self.showing_file_streams {
+            self.handle_file_streams_key_event(key_event);
         } else if let Some(selection) = self.get_selected() {
             match key_event.code {
                 KeyCode::Esc => self.exit = true,
@@ -282,7 +903,7 @@ impl App {
                                     .compress_settings
                                     .video_bitrate
                                     .clone()
-                                    .unwrap_or(String::new()),
+                                    .unwrap_or_default(),
                             )
                         }
                         5 => {
@@ -291,7 +912,7 @@ impl App {
                                     .compress_settings
                                     .audio_bitrate
                                     .clone()
-                                    .unwrap_or(String::new()),
+                                    .unwrap_or_default(),
                             )
                         }
                         6 => {
@@ -300,7 +921,7 @@ impl App {
                                     .compress_settings
                                     .crop
                                     .clone()
-                                    .unwrap_or(String::new()),
+                                    .unwrap_or_default(),
                             );
                         }
                         7 => {
@@ -309,23 +930,122 @@ impl App {
                                     .compress_settings
                                     .scale
                                     .clone()
-                                    .unwrap_or(String::new()),
+                                    .unwrap_or_default(),
                             )
                         }
                         8 => {
                             self.editing_string =
                                 Some(self.ffmpeg_manager.compress_settings.other_settings.clone())
                         }
+                        9 | 11 | 14 | 16 | 18 | 19 => self.selected_compress_setting.select_first(),
+                        12 => {
+                            self.editing_string = Some(
+                                self.ffmpeg_manager
+                                    .compress_settings
+                                    .stream_rules
+                                    .to_string(),
+                            )
+                        }
+                        10 => {
+                            self.editing_string = Some(
+                                self.ffmpeg_manager
+                                    .compress_settings
+                                    .chapter_ranges
+                                    .clone()
+                                    .unwrap_or_default(),
+                            )
+                        }
+                        13 => {
+                            self.editing_string = Some(
+                                self.ffmpeg_manager
+                                    .compress_settings
+                                    .cover
+                                    .clone()
+                                    .unwrap_or_default(),
+                            )
+                        }
+                        15 => {
+                            self.editing_string = Some(
+                                self.ffmpeg_manager
+                                    .compress_settings
+                                    .output_folder
+                                    .clone()
+                                    .unwrap_or_default(),
+                            )
+                        }
+                        17 => {
+                            self.editing_string = Some(
+                                self.ffmpeg_manager
+                                    .compress_settings
+                                    .output_template
+                                    .clone()
+                                    .unwrap_or_default(),
+                            )
+                        }
+                        20 => {
+                            self.editing_string = Some(
+                                self.ffmpeg_manager
+                                    .compress_settings
+                                    .min_saving
+                                    .clone()
+                                    .unwrap_or_default(),
+                            )
+                        }
+                        21 => {
+                            self.editing_string = Some(
+                                self.ffmpeg_manager
+                                    .compress_settings
+                                    .trash_folder
+                                    .clone()
+                                    .unwrap_or_default(),
+                            )
+                        }
+                        22 => {
+                            self.editing_string = Some(
+                                self.ffmpeg_manager
+                                    .compress_settings
+                                    .backup_suffix
+                                    .clone()
+                                    .unwrap_or_default(),
+                            )
+                        }
+                        23 => {
+                            let compress_settings = &mut self.ffmpeg_manager.compress_settings;
+                            compress_settings.copy_attributes = !compress_settings.copy_attributes;
+                        }
+                        24 => {
+                            self.editing_string =
+                                Some(self.ffmpeg_manager.compress_settings.skip_rules.to_string())
+                        }
                         _ => unreachable!(),
                     },
-                    2 => {}
+                    2 if self.selections[2].selected().unwrap()
+                        < self.ffmpeg_manager.input_files.len() =>
+                    {
+                        self.showing_file_streams = true;
+                        self.selected_file_stream.select_first();
+                    }
+                    2 => self.showing_rejected_file = self.get_selected_rejected_file().is_some(),
                     _ => unreachable!(),
                 },
+                KeyCode::PageUp if selection == 0 => {
+                    let selected = self.selections[0].selected().unwrap();
+                    self.ffmpeg_manager.move_stream_settings(selected, -1);
+                    self.selections[0].select_previous();
+                }
+                KeyCode::PageDown if selection == 0 => {
+                    let selected = self.selections[0].selected().unwrap();
+                    self.ffmpeg_manager.move_stream_settings(selected, 1);
+                    self.selections[0].select_next();
+                }
                 KeyCode::Delete if selection == 2 => {
-                    self.ffmpeg_manager
-                        .input_files
-                        .remove(self.selections[selection].selected().unwrap());
-                    self.ffmpeg_manager.update_stream_settings();
+                    let selected = self.selections[selection].selected().unwrap();
+                    if let Some(rejected_file) = self.get_selected_rejected_file() {
+                        self.ffmpeg_manager.rejected_files.remove(rejected_file);
+                    } else if selected < self.ffmpeg_manager.input_files.len() {
+                        self.ffmpeg_manager.input_files.remove(selected);
+                        self.ffmpeg_manager.update_stream_settings();
+                    }
                 }
                 _ => {}
             }
@@ -341,35 +1061,354 @@ impl App {
         self.update_hotkeys();
     }
 
-    fn run_compressing(&mut self) {
-        let mut index = 0usize;
-        let mut output_folder;
-        loop {
-            output_folder = env::current_dir().unwrap().clone();
-            if index == 0 {
-                output_folder.push("output");
-            } else {
-                output_folder.push(format!("output ({})", index));
+    fn handle_file_streams_key_event(&mut self, key_event: KeyEvent) {
+        let file_index = self.selections[2].selected().unwrap();
+        let Some(source_index) = self.selected_file_stream.selected().filter(|&selected| {
+            selected < self.ffmpeg_manager.input_files[file_index].sources.len()
+        }) else {
+            if key_event.code == KeyCode::Esc {
+                self.showing_file_streams = false;
+            }
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => {
+                self.showing_file_streams = false;
+                self.selected_file_stream.select(None);
+            }
+            KeyCode::Up => self.selected_file_stream.select_previous(),
+            KeyCode::Down => self.selected_file_stream.select_next(),
+            KeyCode::Enter => self
+                .ffmpeg_manager
+                .toggle_source_enabled(file_index, source_index),
+            KeyCode::Char('d') if key_event.modifiers.contains(KeyModifiers::CONTROL) => self
+                .ffmpeg_manager
+                .toggle_source_default(file_index, source_index),
+            KeyCode::Backspace => self
+                .ffmpeg_manager
+                .reset_source_override(file_index, source_index),
+            _ => {}
+        }
+    }
+
+    fn handle_chapter_editor_key_event(&mut self, key_event: KeyEvent) {
+        let Some(input_file) = self.selections[2]
+            .selected()
+            .and_then(|selected| self.ffmpeg_manager.input_files.get_mut(selected))
+        else {
+            self.editing_chapters = false;
+            return;
+        };
+        let selected_chapter = self
+            .selected_chapter
+            .selected()
+            .filter(|&selected| selected < input_file.chapters.len());
+        if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+            match key_event.code {
+                KeyCode::Char('a') => {
+                    self.chapter_edit = Some(ChapterEdit::Add);
+                    self.editing_string = Some(String::new());
+                }
+                KeyCode::Char('o') => {
+                    self.chapter_edit = Some(ChapterEdit::Import);
+                    self.editing_string = Some(String::new());
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key_event.code {
+            KeyCode::Esc => {
+                self.editing_chapters = false;
+                self.selected_chapter.select(None);
+            }
+            KeyCode::Up => self.selected_chapter.select_previous(),
+            KeyCode::Down => self.selected_chapter.select_next(),
+            KeyCode::Enter if selected_chapter.is_some() => {
+                self.chapter_edit = Some(ChapterEdit::Rename);
+                self.editing_string = Some(
+                    input_file.chapters[selected_chapter.unwrap()]
+                        .title
+                        .clone()
+                        .unwrap_or_default(),
+                );
+            }
+            KeyCode::Delete if selected_chapter.is_some() => {
+                let selected_chapter = selected_chapter.unwrap();
+                let removed = input_file.chapters.remove(selected_chapter);
+                if selected_chapter == input_file.chapters.len() && selected_chapter > 0 {
+                    input_file.chapters[selected_chapter - 1].end = removed.end;
+                }
+                normalize_chapters(&mut input_file.chapters, input_file.format.duration);
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_chapter_edit(&mut self, chapter_edit: ChapterEdit, editing_string: String) {
+        let Some(input_file) = self.selections[2]
+            .selected()
+            .and_then(|selected| self.ffmpeg_manager.input_files.get_mut(selected))
+        else {
+            return;
+        };
+        match chapter_edit {
+            ChapterEdit::Rename => {
+                let Some(chapter) = self
+                    .selected_chapter
+                    .selected()
+                    .and_then(|selected| input_file.chapters.get_mut(selected))
+                else {
+                    return;
+                };
+                chapter.title =
+                    Some(editing_string.trim().to_string()).filter(|title| !title.is_empty());
             }
-            if !output_folder.exists() {
-                break;
+            ChapterEdit::Add => {
+                let editing_string = editing_string.trim();
+                let (timestamp, title) = editing_string
+                    .split_once(' ')
+                    .unwrap_or((editing_string, ""));
+                let Some(start) = parse_timestamp(timestamp) else {
+                    self.error_message = Some(format!("Invalid chapter start \"{}\"", timestamp));
+                    return;
+                };
+                let end = input_file
+                    .chapters
+                    .iter()
+                    .map(|chapter| chapter.end)
+                    .fold(start, f64::max);
+                input_file.chapters.push(Chapter {
+                    start,
+                    end,
+                    title: Some(title.trim().to_string()).filter(|title| !title.is_empty()),
+                });
+                normalize_chapters(&mut input_file.chapters, input_file.format.duration);
             }
-            index += 1;
+            ChapterEdit::Import => {
+                let path = PathBuf::from(editing_string.trim());
+                match import_chapters(&path, input_file.format.duration) {
+                    Ok(chapters) => input_file.chapters = chapters,
+                    Err(error) => {
+                        self.error_message = Some(format!(
+                            "Chapters are not imported from {}: {}",
+                            path.display(),
+                            error
+                        ))
+                    }
+                }
+            }
+        }
+    }
+
+    fn run_compressing(&mut self) {
+        // A batch started now would miss the files that are not probed yet
+        if self.ffmpeg_manager.probe_pool.is_probing() {
+            self.error_message = Some(String::from(
+                "Files are still being probed, compress when probing is finished",
+            ));
+            return;
         }
-        create_dir(&output_folder).unwrap();
-        self.ffmpeg_manager
+        let segment_errors: Vec<String> = self
+            .ffmpeg_manager
             .input_files
             .iter()
-            .for_each(|input_file| {
-                let mut output_file = output_folder.clone();
-                output_file.push(input_file.path.file_name().unwrap().to_str().unwrap());
-                let ffmpeg_command = self.ffmpeg_manager.get_command(input_file, &output_file);
-                Command::new("ffmpeg")
-                    .args(&ffmpeg_command)
-                    .output()
-                    .unwrap();
+            .filter_map(|input_file| {
+                let error = self.ffmpeg_manager.get_segments(input_file).err()?;
+                Some(format!("{}: {}", input_file.path.display(), error))
+            })
+            .collect();
+        if !segment_errors.is_empty() {
+            self.error_message = Some(format!(
+                "Nothing is compressed, chapters can't be split:\n{}",
+                segment_errors.join("\n")
+            ));
+            return;
+        }
+        let output_folder = self.ffmpeg_manager.get_output_root();
+        // Outputs that overwrite each other are found before anything is written
+        let collisions = self.ffmpeg_manager.get_output_collisions(&output_folder);
+        if !collisions.is_empty() {
+            let mut message = String::from("Nothing is compressed, these outputs collide:");
+            for collision in collisions {
+                message += &format!("\n{}", collision.display());
+            }
+            self.error_message = Some(message);
+            return;
+        }
+        let output_root = match self.ffmpeg_manager.compress_settings.output_location {
+            OutputLocation::OutputFolder => Some(output_folder.clone()),
+            OutputLocation::NextToSource => None,
+        };
+        // Saved after every output, so the batch can be resumed if it is interrupted
+        let mut manifest = Manifest::new(output_root);
+        for input_file in &self.ffmpeg_manager.input_files {
+            let outputs = self.ffmpeg_manager.get_outputs(input_file, &output_folder);
+            if let Some(reason) = self.ffmpeg_manager.get_skip_reason(input_file) {
+                for (output_file, _) in outputs {
+                    manifest.set_state(&input_file.path, &output_file, OutputState::Skipped);
+                    manifest.set_note(&output_file, reason.clone());
+                }
+                let _ = manifest.save();
+                continue;
+            }
+            for (output_file, segment) in outputs {
+                // The input of a resumed batch can already be the compressed file
+                if let Some(entry) = self
+                    .ffmpeg_manager
+                    .manifest
+                    .as_ref()
+                    .and_then(|manifest| manifest.get_entry(&output_file))
+                    && entry.state == OutputState::Replaced
+                {
+                    manifest.set_state(&input_file.path, &output_file, OutputState::Replaced);
+                    let _ = manifest.save();
+                    continue;
+                }
+                let Some(output_file) =
+                    self.ffmpeg_manager
+                        .get_output_path(input_file, &output_file, segment.as_ref())
+                else {
+                    let state = match self.ffmpeg_manager.compress_settings.existing_output {
+                        ExistingOutput::SkipIfValid => OutputState::Done,
+                        _ => OutputState::Skipped,
+                    };
+                    manifest.set_state(&input_file.path, &output_file, state);
+                    let _ = manifest.save();
+                    continue;
+                };
+                manifest.set_state(&input_file.path, &output_file, OutputState::Pending);
+                let _ = manifest.save();
+                // The output gets its name only when it is complete, an interrupted encode
+                // leaves a partial file
+                let partial_file = get_partial_path(&output_file);
+                let chapters_metadata = self
+                    .ffmpeg_manager
+                    .get_chapters_metadata(input_file, segment.as_ref());
+                let prepared = output_file
+                    .parent()
+                    .map_or(Ok(()), create_dir_all)
+                    .and_then(|()| match &chapters_metadata {
+                        Some(chapters_metadata) => {
+                            write(get_metadata_path(&partial_file), chapters_metadata)
+                        }
+                        None => Ok(()),
+                    });
+                if let Err(error) = prepared {
+                    let _ = remove_file(get_metadata_path(&partial_file));
+                    manifest.set_failed(
+                        &input_file.path,
+                        &output_file,
+                        format!("output is not created: {}", error),
+                    );
+                    let _ = manifest.save();
+                    continue;
+                }
+                let ffmpeg_command =
+                    self.ffmpeg_manager
+                        .get_command(input_file, &partial_file, segment.as_ref());
+                let result = match Command::new("ffmpeg").args(&ffmpeg_command).output() {
+                    Ok(output) if output.status.success() => self
+                        .ffmpeg_manager
+                        .verify_output(input_file, &partial_file, segment.as_ref())
+                        .map_err(|error| error.to_string())
+                        .and_then(|()| {
+                            rename(&partial_file, &output_file).map_err(|error| error.to_string())
+                        }),
+                    Ok(output) => Err(format!(
+                        "ffmpeg failed: {}",
+                        String::from_utf8_lossy(&output.stderr)
+                            .lines()
+                            .last()
+                            .unwrap_or_default()
+                            .trim()
+                    )),
+                    Err(error) => Err(error.to_string()),
+                };
+                if chapters_metadata.is_some() {
+                    let _ = remove_file(get_metadata_path(&partial_file));
+                }
+                match result {
+                    Ok(()) => {
+                        manifest.set_state(&input_file.path, &output_file, OutputState::Done);
+                        if self.ffmpeg_manager.compress_settings.copy_attributes
+                            && let Err(error) = copy_attributes(&input_file.path, &output_file)
+                        {
+                            manifest.set_note(
+                                &output_file,
+                                format!("attributes are not copied: {}", error),
+                            );
+                        }
+                        let replace_original =
+                            &self.ffmpeg_manager.compress_settings.replace_original;
+                        if *replace_original != ReplaceOriginal::No {
+                            let replaced = match segment {
+                                Some(_) => Err(String::from("split by chapters")),
+                                None => self
+                                    .ffmpeg_manager
+                                    .replace_original(input_file, &output_file),
+                            };
+                            match replaced {
+                                Ok(note) => {
+                                    if *replace_original == ReplaceOriginal::Yes {
+                                        manifest.set_state(
+                                            &input_file.path,
+                                            &output_file,
+                                            OutputState::Replaced,
+                                        );
+                                    }
+                                    manifest.set_note(&output_file, note);
+                                }
+                                Err(reason) => manifest
+                                    .set_note(&output_file, format!("original kept: {}", reason)),
+                            }
+                        }
+                    }
+                    Err(error) => {
+                        let _ = remove_file(&partial_file);
+                        manifest.set_failed(&input_file.path, &output_file, error);
+                    }
+                }
+                let _ = manifest.save();
+            }
+        }
+        if manifest.is_finished() {
+            let _ = Manifest::remove();
+        }
+        self.results = Some(manifest);
+        self.update_hotkeys();
+    }
+
+    fn render_results(&self, results: &Manifest, area: Rect, buf: &mut Buffer) {
+        let count = |state| {
+            results
+                .entries
+                .iter()
+                .filter(|entry| entry.state == state)
+                .count()
+        };
+        let mut lines = vec![Line::from(format!(
+            "{} done, {} replaced, {} failed, {} skipped",
+            count(OutputState::Done),
+            count(OutputState::Replaced),
+            count(OutputState::Failed),
+            count(OutputState::Skipped)
+        ))];
+        // Failed outputs go first, so they are visible in long batches
+        let mut entries: Vec<&ManifestEntry> = results.entries.iter().collect();
+        entries.sort_by_key(|entry| entry.state != OutputState::Failed);
+        for entry in entries {
+            let mut line = format!("{}: {}", entry.state, entry.output.display());
+            if let Some(note) = &entry.note {
+                line += &format!(" ({})", note);
+            }
+            lines.push(match &entry.error {
+                Some(error) => Line::from(format!("{} ({})", line, error).red()),
+                None if entry.state == OutputState::Skipped => Line::from(line.dark_gray()),
+                None => Line::from(line),
             });
-        self.exit = true;
+        }
+        render_popup(" Results ", lines, area, buf);
     }
 
     fn render_settings(&mut self, area: Rect, buf: &mut Buffer) {
@@ -385,11 +1424,53 @@ impl App {
         let sources_block = Block::bordered()
             .title(Line::from(" Sources ").centered())
             .border_set(border::ROUNDED);
+        if self.selected_disposition.selected().is_some() {
+            let disposition = &self.ffmpeg_manager.stream_settings
+                [self.selections[0].selected().unwrap()]
+            .disposition;
+            let items: Vec<ListItem> = DISPOSITION_FLAGS
+                .iter()
+                .enumerate()
+                .map(|(i, flag)| {
+                    let checkbox = if disposition.get_flag(i) {
+                        "[X] "
+                    } else {
+                        "[ ] "
+                    };
+                    ListItem::from(format!("{}{}", checkbox, flag))
+                })
+                .collect();
+            let list = List::new(items).block(sources_block).highlight_symbol(">");
+            StatefulWidget::render(list, area, buf, &mut self.selected_disposition);
+            return;
+        }
+        if self.selected_language.selected().is_some() {
+            let mut items = vec![ListItem::from("Keep source language")];
+            items.extend(
+                LANGUAGES
+                    .iter()
+                    .map(|(code, _, name)| ListItem::from(format!("{} - {}", code, name))),
+            );
+            let list = List::new(items).block(sources_block).highlight_symbol(">");
+            StatefulWidget::render(list, area, buf, &mut self.selected_language);
+            return;
+        }
         let items: Vec<ListItem> = self
             .ffmpeg_manager
             .stream_settings
             .iter()
-            .map(|source| ListItem::from(source.to_string()))
+            .map(|source| {
+                let mut result = source.to_string();
+                let overriding_files = self.ffmpeg_manager.get_overriding_files(source);
+                if !overriding_files.is_empty() {
+                    let overriding_files = overriding_files
+                        .iter()
+                        .map(|f| f.to_string())
+                        .collect::<Vec<String>>();
+                    result += &format!(" [overridden in {}]", overriding_files.join("|"));
+                }
+                ListItem::from(result)
+            })
             .collect();
         let list = List::new(items).block(sources_block).highlight_symbol(">");
         StatefulWidget::render(list, area, buf, &mut self.selections[0]);
@@ -450,18 +1531,114 @@ impl App {
                         ListItem::new(result)
                     })
                     .collect(),
+                9 => ChapterSplit::iter()
+                    .map(|split| {
+                        let mut result = String::new();
+                        result += if split == self.ffmpeg_manager.compress_settings.chapter_split {
+                            "[X] "
+                        } else {
+                            "[ ] "
+                        };
+                        result += &split.to_string();
+                        ListItem::new(result)
+                    })
+                    .collect(),
+                11 => ChapterMapping::iter()
+                    .map(|mapping| {
+                        let mut result = String::new();
+                        result +=
+                            if mapping == self.ffmpeg_manager.compress_settings.chapter_mapping {
+                                "[X] "
+                            } else {
+                                "[ ] "
+                            };
+                        result += &mapping.to_string();
+                        ListItem::new(result)
+                    })
+                    .collect(),
+                14 => Container::iter()
+                    .map(|container| {
+                        let mut result = String::new();
+                        result += if container == self.ffmpeg_manager.compress_settings.container {
+                            "[X] "
+                        } else {
+                            "[ ] "
+                        };
+                        result += &container.to_string();
+                        ListItem::new(result)
+                    })
+                    .collect(),
+                16 => OutputLocation::iter()
+                    .map(|location| {
+                        let mut result = String::new();
+                        result +=
+                            if location == self.ffmpeg_manager.compress_settings.output_location {
+                                "[X] "
+                            } else {
+                                "[ ] "
+                            };
+                        result += &location.to_string();
+                        ListItem::new(result)
+                    })
+                    .collect(),
+                18 => ExistingOutput::iter()
+                    .map(|existing_output| {
+                        let mut result = String::new();
+                        result += if existing_output
+                            == self.ffmpeg_manager.compress_settings.existing_output
+                        {
+                            "[X] "
+                        } else {
+                            "[ ] "
+                        };
+                        result += &existing_output.to_string();
+                        ListItem::new(result)
+                    })
+                    .collect(),
+                19 => ReplaceOriginal::iter()
+                    .map(|replace_original| {
+                        let mut result = String::new();
+                        result += if replace_original
+                            == self.ffmpeg_manager.compress_settings.replace_original
+                        {
+                            "[X] "
+                        } else {
+                            "[ ] "
+                        };
+                        result += &replace_original.to_string();
+                        ListItem::new(result)
+                    })
+                    .collect(),
                 _ => unreachable!(),
             };
             let list = List::new(items).block(settings_block).highlight_symbol(">");
             StatefulWidget::render(list, area, buf, &mut self.selected_compress_setting);
-        } else if let Some(editing_string) = self.editing_string.clone() {
-            let title = match self.selections[1].selected().unwrap() {
-                4 => "Video bitrate",
-                5 => "Audio bitrate",
-                6 => "Crop",
-                7 => "Scale",
-                8 => "Other settings",
-                _ => unreachable!(),
+        } else if let Some(editing_string) = self.editing_string.clone()
+            && self.chapter_edit.is_none()
+        {
+            let title = match (&self.preset_edit, self.selections[1].selected()) {
+                (Some(PresetEdit::Save), _) => "Save preset as",
+                (Some(PresetEdit::Load), _) => "Load preset",
+                (None, _) if self.editing_title => "Stream title (empty to remove)",
+                (None, selection) => match selection.unwrap() {
+                    4 => "Video bitrate",
+                    5 => "Audio bitrate",
+                    6 => "Crop",
+                    7 => "Scale",
+                    8 => "Other settings",
+                    10 => "Chapter ranges (1-3,4,...)",
+                    12 => {
+                        "Stream rules (audio rus,eng; default audio rus; subtitles eng; forced subtitles default)"
+                    }
+                    13 => "Cover image (empty to keep source, relative to input folder)",
+                    15 => "Output folder (empty for auto)",
+                    17 => "File name ({stem} {codec} {height} {ext} {date})",
+                    20 => "Min saving, % of the original size (empty for 10%)",
+                    21 => "Trash folder for replaced originals (empty to not keep them)",
+                    22 => "Backup suffix of replaced originals (empty to not keep them)",
+                    24 => "Skip rules (codecs av1,hevc; bpp 0.05; size 200)",
+                    _ => unreachable!(),
+                },
             };
             let input =
                 Paragraph::new(editing_string.clone()).block(Block::bordered().title(title));
@@ -480,22 +1657,175 @@ impl App {
         }
     }
 
+    fn render_rejected_file(&mut self, area: Rect, buf: &mut Buffer) {
+        let Some(rejected_file) = self
+            .get_selected_rejected_file()
+            .map(|i| &self.ffmpeg_manager.rejected_files[i])
+        else {
+            return;
+        };
+        let lines = vec![
+            Line::from(format!("Path: {}", rejected_file.path.display())),
+            Line::from(format!("Reason: {}", rejected_file.get_reason())),
+            Line::from(format!("Error: {}", rejected_file.error)),
+        ];
+        render_popup(" Rejected file ", lines, area, buf);
+    }
+
     fn render_files_list(&mut self, area: Rect, buf: &mut Buffer) {
+        let probe_pool = &self.ffmpeg_manager.probe_pool;
+        let files_title = if probe_pool.is_probing() {
+            format!(
+                " Files (probing {}/{}) ",
+                probe_pool.done, probe_pool.queued
+            )
+        } else {
+            String::from(" Files ")
+        };
         let files_block = Block::bordered()
-            .title(Line::from(" Files ").centered())
+            .title(Line::from(files_title).centered())
             .border_set(border::ROUNDED);
 
-        let items: Vec<ListItem> = self
+        let selected_file = self.selections[2]
+            .selected()
+            .and_then(|selected| self.ffmpeg_manager.input_files.get(selected));
+        let chapters = selected_file
+            .map(|file| file.chapters.as_slice())
+            .unwrap_or_default();
+        let [files_rect, chapters_rect] =
+            if chapters.is_empty() && !self.editing_chapters && !self.showing_file_streams {
+                [area, Rect::default()]
+            } else {
+                Layout::vertical([Fill(1), Fill(1)]).areas(area)
+            };
+        // File streams view shows details of the selected stream instead of the file
+        let selected_stream = selected_file
+            .filter(|_| self.showing_file_streams)
+            .zip(self.selected_file_stream.selected())
+            .and_then(|(file, selected)| file.sources.get(selected));
+        let details = match (selected_stream, selected_file) {
+            (Some(stream), _) => stream.get_details(),
+            (None, Some(file)) => file.format.get_details(),
+            (None, None) => Vec::new(),
+        };
+        let [files_rect, details_rect] = if details.is_empty() {
+            [files_rect, Rect::default()]
+        } else {
+            Layout::vertical([Fill(1), Length(details.len().min(8) as u16 + 2)]).areas(files_rect)
+        };
+        if !details.is_empty() {
+            let details_block = Block::bordered()
+                .title(Line::from(" Details ").centered())
+                .border_set(border::ROUNDED);
+            let lines: Vec<Line> = details.into_iter().map(Line::from).collect();
+            Paragraph::new(lines)
+                .block(details_block)
+                .render(details_rect, buf);
+        }
+
+        let mut items: Vec<ListItem> = self
             .ffmpeg_manager
             .input_files
             .iter()
-            .map(|file| ListItem::from(file.path.file_name().unwrap().to_string_lossy()))
+            .map(|file| {
+                let mut result = String::new();
+                if file.has_overrides() {
+                    result += "* ";
+                }
+                if !self.ffmpeg_manager.get_warnings(file).is_empty() {
+                    result += "! ";
+                }
+                result += &file.path.file_name().unwrap().to_string_lossy();
+                match self.ffmpeg_manager.get_skip_reason(file) {
+                    Some(reason) => {
+                        ListItem::from(format!("{} (skip: {})", result, reason)).dark_gray()
+                    }
+                    None => ListItem::from(result),
+                }
+            })
             .collect();
+        items.extend(
+            self.ffmpeg_manager
+                .rejected_files
+                .iter()
+                .map(|rejected_file| {
+                    let file_name = rejected_file
+                        .path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy();
+                    ListItem::from(format!("{} ({})", file_name, rejected_file.get_reason()))
+                        .dark_gray()
+                }),
+        );
         let list = List::new(items).block(files_block).highlight_symbol(">");
-        StatefulWidget::render(list, area, buf, &mut self.selections[2]);
+
+        if let Some(chapter_edit) = &self.chapter_edit {
+            let editing_string = self.editing_string.clone().unwrap_or_default();
+            let title = match chapter_edit {
+                ChapterEdit::Rename => "Chapter title",
+                ChapterEdit::Add => "New chapter (HH:MM:SS title)",
+                ChapterEdit::Import => "Chapters file (OGM/XML)",
+            };
+            let input =
+                Paragraph::new(editing_string.clone()).block(Block::bordered().title(title));
+            input.render(chapters_rect, buf);
+            self.cursor_position = (
+                chapters_rect.x + editing_string.len() as u16 + 1,
+                chapters_rect.y + 1,
+            );
+        } else if self.showing_file_streams
+            && let Some(selected_file) = selected_file
+        {
+            let streams_block = Block::bordered()
+                .title(Line::from(" File streams ").centered())
+                .border_set(border::ROUNDED);
+            let items: Vec<ListItem> = (0..selected_file.sources.len())
+                .map(|i| {
+                    ListItem::from(self.ffmpeg_manager.get_source_description(selected_file, i))
+                })
+                .collect();
+            let list = List::new(items).block(streams_block).highlight_symbol(">");
+            StatefulWidget::render(list, chapters_rect, buf, &mut self.selected_file_stream);
+        } else if !chapters.is_empty() || self.editing_chapters {
+            let chapters_block = Block::bordered()
+                .title(Line::from(" Chapters ").centered())
+                .border_set(border::ROUNDED);
+            let items: Vec<ListItem> = chapters
+                .iter()
+                .enumerate()
+                .map(|(i, chapter)| ListItem::from(format!("{}. {}", i + 1, chapter)))
+                .collect();
+            let list = List::new(items).block(chapters_block).highlight_symbol(">");
+            if self.editing_chapters {
+                StatefulWidget::render(list, chapters_rect, buf, &mut self.selected_chapter);
+            } else {
+                Widget::render(list, chapters_rect, buf);
+            }
+        }
+        StatefulWidget::render(list, files_rect, buf, &mut self.selections[2]);
     }
 }
 
+/// Renders lines in a bordered block over the center of the area
+fn render_popup(title: &str, lines: Vec<Line>, area: Rect, buf: &mut Buffer) {
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let [popup_rect] = Layout::vertical([Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup_rect] = Layout::horizontal([Percentage(60)])
+        .flex(Flex::Center)
+        .areas(popup_rect);
+    let popup_block = Block::bordered()
+        .title(Line::from(title).centered())
+        .border_set(border::ROUNDED);
+    Clear.render(popup_rect, buf);
+    Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(popup_block)
+        .render(popup_rect, buf);
+}
+
 impl Widget for &mut App {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let title = Line::from(" Film Compressor ".bold());
@@ -513,12 +1843,42 @@ impl Widget for &mut App {
         let command_block = Block::bordered()
             .border_set(border::ROUNDED)
             .title(Line::from(" Command ").centered());
-        let ffmpeg_command = Paragraph::new(self.ffmpeg_manager.get_command_template())
+        let mut command_lines = vec![Line::from(self.ffmpeg_manager.get_command_template())];
+        if let Some(input_file) = self.selections[2]
+            .selected()
+            .and_then(|selected| self.ffmpeg_manager.input_files.get(selected))
+        {
+            let output_root = self.ffmpeg_manager.get_output_root();
+            if let Some((output_file, _)) = self
+                .ffmpeg_manager
+                .get_outputs(input_file, &output_root)
+                .first()
+            {
+                command_lines.push(Line::from(format!("Output: {}", output_file.display())));
+            }
+            command_lines.extend(
+                self.ffmpeg_manager
+                    .get_warnings(input_file)
+                    .into_iter()
+                    .map(|warning| Line::from(format!("Warning: {}", warning).yellow())),
+            );
+        }
+        let ffmpeg_command = Paragraph::new(command_lines)
             .wrap(Wrap { trim: false })
             .block(command_block);
         block.render(area, buf);
         ffmpeg_command.render(command, buf);
 
         self.render_settings(main_page, buf);
+        if self.showing_rejected_file {
+            self.render_rejected_file(main_page, buf);
+        }
+        if let Some(error_message) = &self.error_message {
+            let lines = error_message.lines().map(Line::from).collect();
+            render_popup(" Error ", lines, main_page, buf);
+        }
+        if let Some(results) = &self.results {
+            self.render_results(results, main_page, buf);
+        }
     }
 }
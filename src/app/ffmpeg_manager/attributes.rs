@@ -0,0 +1,51 @@
+use std::fs::{File, FileTimes, Metadata, set_permissions};
+use std::io::Error;
+use std::path::Path;
+
+/// Sets modification and access times and permissions of the source to the target
+pub fn copy_times_and_permissions(source: &Metadata, target: &Path) -> Result<(), Error> {
+    let mut times = FileTimes::new();
+    if let Ok(modified) = source.modified() {
+        times = times.set_modified(modified);
+    }
+    if let Ok(accessed) = source.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    File::options().write(true).open(target)?.set_times(times)?;
+    set_permissions(target, source.permissions())
+}
+
+/// Copies "user." extended attributes. File systems without them are skipped.
+#[cfg(unix)]
+pub fn copy_xattrs(source: &Path, target: &Path) -> Result<(), Error> {
+    use std::io::ErrorKind;
+    use std::os::unix::ffi::OsStrExt;
+
+    let names = match xattr::list(source) {
+        Ok(names) => names,
+        Err(error) if error.kind() == ErrorKind::Unsupported => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    for name in names.filter(|name| name.as_bytes().starts_with(b"user.")) {
+        if let Some(value) = xattr::get(source, &name)? {
+            match xattr::set(target, &name, &value) {
+                Err(error) if error.kind() == ErrorKind::Unsupported => return Ok(()),
+                result => result?,
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn copy_xattrs(_source: &Path, _target: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Copies times, permissions and extended attributes of the source to the target
+pub fn copy_attributes(source: &Path, target: &Path) -> Result<(), Error> {
+    let metadata = source.metadata()?;
+    // Extended attributes first, setting them can change the times on some systems
+    copy_xattrs(source, target)?;
+    copy_times_and_permissions(&metadata, target)
+}
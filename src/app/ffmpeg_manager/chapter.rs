@@ -0,0 +1,468 @@
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use strum_macros::EnumIter;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub start: f64,
+    pub end: f64,
+    pub title: Option<String>,
+}
+
+impl Chapter {
+    pub fn from_json(chapter: &Value) -> Option<Self> {
+        Some(Self {
+            start: chapter["start_time"].as_str()?.parse().ok()?,
+            end: chapter["end_time"].as_str()?.parse().ok()?,
+            title: chapter["tags"]["title"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the chapter moved into segment time or None if it is outside the segment
+    pub fn in_segment(&self, segment: &ChapterSegment) -> Option<Self> {
+        let start = self.start.max(segment.start) - segment.start;
+        let end = self.end.min(segment.end) - segment.start;
+        if end <= start {
+            return None;
+        }
+        Some(Self {
+            start,
+            end,
+            title: self.title.clone(),
+        })
+    }
+}
+
+impl Display for Chapter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            format_timestamp(self.start),
+            format_timestamp(self.end)
+        )?;
+        if let Some(title) = &self.title {
+            write!(f, " {}", title)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats seconds as HH:MM:SS
+pub fn format_timestamp(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Parses "HH:MM:SS.fff", "MM:SS" or seconds
+pub fn parse_timestamp(timestamp: &str) -> Option<f64> {
+    let mut result = 0.0;
+    for part in timestamp.trim().split(':') {
+        let value: f64 = part.parse().ok()?;
+        if value < 0.0 {
+            return None;
+        }
+        result = result * 60.0 + value;
+    }
+    Some(result)
+}
+
+/// Sorts chapters and makes each chapter end where the next one starts. The last chapter
+/// ends with the file if its duration is known, OGM files and added chapters have no end.
+pub fn normalize_chapters(chapters: &mut [Chapter], duration: Option<f64>) {
+    chapters.sort_by(|a, b| a.start.total_cmp(&b.start));
+    for i in 0..chapters.len() {
+        chapters[i].end = match (chapters.get(i + 1), duration) {
+            (Some(next), _) => next.start,
+            (None, Some(duration)) if duration > chapters[i].start => duration,
+            (None, _) => chapters[i].end.max(chapters[i].start),
+        };
+    }
+}
+
+/// Reads chapters from OGM (CHAPTER01=...) or Matroska XML chapter file
+pub fn import_chapters(path: &Path, duration: Option<f64>) -> Result<Vec<Chapter>, Error> {
+    let content = read_to_string(path)?;
+    let mut chapters = if content.contains("<ChapterAtom>") {
+        parse_xml_chapters(&content)
+    } else {
+        parse_ogm_chapters(&content)
+    };
+    if chapters.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, "No chapters found"));
+    }
+    normalize_chapters(&mut chapters, duration);
+    Ok(chapters)
+}
+
+fn parse_ogm_chapters(content: &str) -> Vec<Chapter> {
+    let mut result: Vec<Chapter> = Vec::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        if !key.starts_with("CHAPTER") {
+            continue;
+        }
+        if key.ends_with("NAME") {
+            if let Some(chapter) = result.last_mut() {
+                chapter.title = Some(value.to_string()).filter(|title| !title.is_empty());
+            }
+        } else if let Some(start) = parse_timestamp(value) {
+            result.push(Chapter {
+                start,
+                end: start,
+                title: None,
+            });
+        }
+    }
+    result
+}
+
+fn parse_xml_chapters(content: &str) -> Vec<Chapter> {
+    content
+        .split("<ChapterAtom>")
+        .skip(1)
+        .filter_map(|atom| {
+            let start = parse_timestamp(get_xml_tag(atom, "ChapterTimeStart")?)?;
+            let end = get_xml_tag(atom, "ChapterTimeEnd")
+                .and_then(parse_timestamp)
+                .unwrap_or(start);
+            Some(Chapter {
+                start,
+                end,
+                title: get_xml_tag(atom, "ChapterString").map(|s| s.to_string()),
+            })
+        })
+        .collect()
+}
+
+fn get_xml_tag<'a>(content: &'a str, tag: &str) -> Option<&'a str> {
+    let start = content.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = content[start..].find(&format!("</{}>", tag))? + start;
+    Some(content[start..end].trim())
+}
+
+/// Returns chapters in FFMETADATA format
+pub fn to_ffmetadata(chapters: &[Chapter]) -> String {
+    let mut result = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        result += "[CHAPTER]\nTIMEBASE=1/1000\n";
+        result += &format!("START={}\n", (chapter.start * 1000.0).round() as u64);
+        result += &format!("END={}\n", (chapter.end * 1000.0).round() as u64);
+        if let Some(title) = &chapter.title {
+            let mut escaped = String::new();
+            for c in title.chars() {
+                if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            result += &format!("title={}\n", escaped);
+        }
+    }
+    result
+}
+
+/// Path of the temporary FFMETADATA file for the output
+pub fn get_metadata_path(output_path: &Path) -> PathBuf {
+    let mut result = output_path.to_path_buf();
+    result.add_extension("ffmetadata");
+    result
+}
+
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
+pub enum ChapterMapping {
+    #[default]
+    Keep,
+    Drop,
+}
+
+impl Display for ChapterMapping {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChapterMapping::Keep => f.write_str("keep"),
+            ChapterMapping::Drop => f.write_str("drop"),
+        }
+    }
+}
+
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
+pub enum ChapterSplit {
+    #[default]
+    No,
+    Each,
+    Ranges,
+}
+
+impl Display for ChapterSplit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChapterSplit::No => f.write_str("no"),
+            ChapterSplit::Each => f.write_str("each chapter"),
+            ChapterSplit::Ranges => f.write_str("chapter ranges"),
+        }
+    }
+}
+
+/// Part of an input file that is written to its own output
+#[derive(Clone)]
+pub struct ChapterSegment {
+    pub start: f64,
+    pub end: f64,
+    pub name: String,
+}
+
+impl ChapterSplit {
+    /// Returns segments to write for given chapters. Empty result means the whole file.
+    ///
+    /// `ranges` is a comma separated list of 1-based chapter numbers or ranges: "1-3,4,5-6".
+    /// Segment names start with `stem`, so outputs of different inputs in one folder differ.
+    pub fn get_segments(
+        &self,
+        chapters: &[Chapter],
+        ranges: Option<&str>,
+        stem: &str,
+    ) -> Result<Vec<ChapterSegment>, String> {
+        if chapters.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ranges = match self {
+            ChapterSplit::No => return Ok(Vec::new()),
+            ChapterSplit::Each => (0..chapters.len()).map(|i| (i, i)).collect(),
+            ChapterSplit::Ranges => parse_ranges(ranges.unwrap_or_default(), chapters.len())?,
+        };
+        Ok(ranges
+            .into_iter()
+            .map(|(first, last)| {
+                let title = chapters[first]
+                    .title
+                    .clone()
+                    .unwrap_or(format!("Chapter {}", first + 1));
+                let name = if first == last {
+                    format!("{} - {:02}. {}", stem, first + 1, title)
+                } else {
+                    format!("{} - {:02}-{:02}. {}", stem, first + 1, last + 1, title)
+                };
+                ChapterSegment {
+                    start: chapters[first].start,
+                    end: chapters[last].end,
+                    name: sanitize_file_name(&name),
+                }
+            })
+            .collect())
+    }
+}
+
+/// Parses "1-3,4" into zero-based inclusive ranges
+fn parse_ranges(ranges: &str, chapters_count: usize) -> Result<Vec<(usize, usize)>, String> {
+    if ranges.trim().is_empty() {
+        return Err(String::from("No chapter ranges are set"));
+    }
+    ranges
+        .split(',')
+        .map(|range| {
+            let parse = |chapter: &str| chapter.trim().parse::<usize>().ok();
+            let parsed = match range.split_once('-') {
+                Some((first, last)) => parse(first).zip(parse(last)),
+                None => parse(range).map(|chapter| (chapter, chapter)),
+            };
+            match parsed {
+                Some((first, last)) if first > 0 && first <= last && last <= chapters_count => {
+                    Ok((first - 1, last - 1))
+                }
+                Some(_) => Err(format!(
+                    "Chapter range \"{}\" is outside of {} chapters",
+                    range.trim(),
+                    chapters_count
+                )),
+                None => Err(format!("Invalid chapter range \"{}\"", range.trim())),
+            }
+        })
+        .collect()
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_chapters(count: usize) -> Vec<Chapter> {
+        (0..count)
+            .map(|i| Chapter {
+                start: i as f64 * 10.0,
+                end: (i + 1) as f64 * 10.0,
+                title: (i == 0).then(|| String::from("Intro: part")),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(
+            parse_ranges("1-3, 4,5-5", 5),
+            Ok(vec![(0, 2), (3, 3), (4, 4)])
+        );
+        assert!(parse_ranges("", 5).is_err());
+        assert!(parse_ranges(" ", 5).is_err());
+        assert!(parse_ranges("1,x", 5).is_err());
+        assert!(parse_ranges("0-2", 5).is_err());
+        assert!(parse_ranges("3-2", 5).is_err());
+        assert!(parse_ranges("4-6", 5).is_err());
+    }
+
+    #[test]
+    fn names_segments_after_input_and_chapters() {
+        let chapters = get_chapters(3);
+        let segments = ChapterSplit::Each
+            .get_segments(&chapters, None, "Movie")
+            .unwrap();
+        let names: Vec<&str> = segments.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "Movie - 01. Intro_ part",
+                "Movie - 02. Chapter 2",
+                "Movie - 03. Chapter 3"
+            ]
+        );
+        let segments = ChapterSplit::Ranges
+            .get_segments(&chapters, Some("2-3"), "Movie")
+            .unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].name, "Movie - 02-03. Chapter 2");
+        assert_eq!((segments[0].start, segments[0].end), (10.0, 30.0));
+    }
+
+    #[test]
+    fn rejects_invalid_ranges_instead_of_whole_file() {
+        let chapters = get_chapters(3);
+        assert!(
+            ChapterSplit::Ranges
+                .get_segments(&chapters, None, "a")
+                .is_err()
+        );
+        assert!(
+            ChapterSplit::Ranges
+                .get_segments(&chapters, Some("1-9"), "a")
+                .is_err()
+        );
+        assert!(
+            ChapterSplit::No
+                .get_segments(&chapters, None, "a")
+                .unwrap()
+                .is_empty()
+        );
+        // Files without chapters are written whole
+        assert!(
+            ChapterSplit::Ranges
+                .get_segments(&[], Some("1"), "a")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(parse_timestamp("01:02:03.5"), Some(3723.5));
+        assert_eq!(parse_timestamp("02:03"), Some(123.0));
+        assert_eq!(parse_timestamp(" 42 "), Some(42.0));
+        assert_eq!(parse_timestamp("1:x"), None);
+        assert_eq!(parse_timestamp("-5"), None);
+        assert_eq!(format_timestamp(3723.5), "01:02:03");
+    }
+
+    #[test]
+    fn parses_ogm_chapters() {
+        let content = "CHAPTER01=00:00:00.000\nCHAPTER01NAME=Intro\n\
+                       CHAPTER02=00:01:30.000\nCHAPTER02NAME=\n";
+        let mut chapters = parse_ogm_chapters(content);
+        normalize_chapters(&mut chapters, Some(150.0));
+        assert_eq!(
+            chapters,
+            [
+                Chapter {
+                    start: 0.0,
+                    end: 90.0,
+                    title: Some(String::from("Intro")),
+                },
+                Chapter {
+                    start: 90.0,
+                    end: 150.0,
+                    title: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ends_last_chapter_with_file() {
+        let mut chapters = get_chapters(2);
+        // Added past the last chapter, it has no end of its own
+        chapters.push(Chapter {
+            start: 25.0,
+            end: 25.0,
+            title: None,
+        });
+        normalize_chapters(&mut chapters, Some(40.0));
+        let bounds: Vec<(f64, f64)> = chapters.iter().map(|c| (c.start, c.end)).collect();
+        assert_eq!(bounds, [(0.0, 10.0), (10.0, 25.0), (25.0, 40.0)]);
+        let segments = ChapterSplit::Each
+            .get_segments(&chapters, None, "a")
+            .unwrap();
+        assert!(segments.iter().all(|segment| segment.end > segment.start));
+        // Without the duration the end is kept, a duration before the start is ignored
+        normalize_chapters(&mut chapters, None);
+        assert_eq!(chapters[2].end, 40.0);
+        normalize_chapters(&mut chapters, Some(20.0));
+        assert_eq!(chapters[2].end, 40.0);
+    }
+
+    #[test]
+    fn parses_xml_chapters() {
+        let content = "<Chapters><EditionEntry>\
+            <ChapterAtom><ChapterTimeStart>00:00:10.000000000</ChapterTimeStart>\
+            <ChapterTimeEnd>00:00:20.000000000</ChapterTimeEnd>\
+            <ChapterDisplay><ChapterString>Second</ChapterString></ChapterDisplay></ChapterAtom>\
+            <ChapterAtom><ChapterTimeStart>00:00:00.000000000</ChapterTimeStart></ChapterAtom>\
+            </EditionEntry></Chapters>";
+        let mut chapters = parse_xml_chapters(content);
+        normalize_chapters(&mut chapters, None);
+        assert_eq!(chapters.len(), 2);
+        assert_eq!((chapters[0].start, chapters[0].end), (0.0, 10.0));
+        assert_eq!(chapters[0].title, None);
+        assert_eq!((chapters[1].start, chapters[1].end), (10.0, 20.0));
+        assert_eq!(chapters[1].title.as_deref(), Some("Second"));
+    }
+
+    #[test]
+    fn escapes_ffmetadata_titles() {
+        let chapters = [Chapter {
+            start: 1.5,
+            end: 2.0,
+            title: Some(String::from("a=b;c")),
+        }];
+        assert_eq!(
+            to_ffmetadata(&chapters),
+            ";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=1500\nEND=2000\ntitle=a\\=b\\;c\n"
+        );
+    }
+}
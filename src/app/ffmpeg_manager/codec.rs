@@ -1,23 +1,34 @@
 use std::fmt::{Display, Formatter};
 use strum_macros::EnumIter;
 
-#[derive(Debug, EnumIter, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
 pub enum VideoCodec {
     Libx264,
     H264Vaapi,
     HevcVaapi,
     Libsvtav1,
+    #[default]
     Av1Vaapi,
     Copy,
 }
 
 impl VideoCodec {
-    pub fn is_vaapi(&self) -> bool {
+    /// Returns name of the output format: "av1" for av1_vaapi, None for copy
+    pub fn get_format_name(&self) -> Option<&'static str> {
         match self {
-            VideoCodec::H264Vaapi | VideoCodec::HevcVaapi | VideoCodec::Av1Vaapi => true,
-            _ => false,
+            VideoCodec::Libx264 | VideoCodec::H264Vaapi => Some("h264"),
+            VideoCodec::HevcVaapi => Some("hevc"),
+            VideoCodec::Libsvtav1 | VideoCodec::Av1Vaapi => Some("av1"),
+            VideoCodec::Copy => None,
         }
     }
+
+    pub fn is_vaapi(&self) -> bool {
+        matches!(
+            self,
+            VideoCodec::H264Vaapi | VideoCodec::HevcVaapi | VideoCodec::Av1Vaapi
+        )
+    }
 }
 
 impl Display for VideoCodec {
@@ -33,14 +44,9 @@ impl Display for VideoCodec {
     }
 }
 
-impl Default for VideoCodec {
-    fn default() -> Self {
-        VideoCodec::Av1Vaapi
-    }
-}
-
-#[derive(Debug, EnumIter, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
 pub enum AudioCodec {
+    #[default]
     Libopus,
     Copy,
 }
@@ -54,15 +60,10 @@ impl Display for AudioCodec {
     }
 }
 
-impl Default for AudioCodec {
-    fn default() -> Self {
-        AudioCodec::Libopus
-    }
-}
-
-#[derive(Debug, EnumIter, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
 pub enum SubtitleCodec {
     Srt,
+    #[default]
     Ass,
     Copy,
 }
@@ -77,16 +78,11 @@ impl Display for SubtitleCodec {
     }
 }
 
-impl Default for SubtitleCodec {
-    fn default() -> Self {
-        SubtitleCodec::Ass
-    }
-}
-
-#[derive(Debug, EnumIter, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
 pub enum PixelFormat {
     Yuv420p,
     Nv10,
+    #[default]
     Yuv420p10le,
     Copy,
 }
@@ -102,8 +98,30 @@ impl Display for PixelFormat {
     }
 }
 
-impl Default for PixelFormat {
-    fn default() -> Self {
-        PixelFormat::Yuv420p10le
+/// Output container, the input container is kept by default
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
+pub enum Container {
+    #[default]
+    Source,
+    Mkv,
+    Mp4,
+    Webm,
+}
+
+impl Container {
+    /// Returns extension of the output file, None keeps the input extension
+    pub fn get_extension(&self) -> Option<&'static str> {
+        match self {
+            Container::Source => None,
+            Container::Mkv => Some("mkv"),
+            Container::Mp4 => Some("mp4"),
+            Container::Webm => Some("webm"),
+        }
+    }
+}
+
+impl Display for Container {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.get_extension().unwrap_or("source"))
     }
 }
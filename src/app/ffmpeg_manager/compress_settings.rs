@@ -1,6 +1,13 @@
-use crate::app::ffmpeg_manager::codec::{AudioCodec, PixelFormat, SubtitleCodec, VideoCodec};
+use crate::app::ffmpeg_manager::chapter::{ChapterMapping, ChapterSplit};
+use crate::app::ffmpeg_manager::codec::{
+    AudioCodec, Container, PixelFormat, SubtitleCodec, VideoCodec,
+};
+use crate::app::ffmpeg_manager::output::{DEFAULT_TEMPLATE, ExistingOutput, OutputLocation};
+use crate::app::ffmpeg_manager::replace::{DEFAULT_MIN_SAVING, ReplaceOriginal, parse_saving};
+use crate::app::ffmpeg_manager::skip_rules::SkipRules;
+use crate::app::ffmpeg_manager::stream_rules::StreamRules;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct CompressSettings {
     pub video_codec: VideoCodec,
     pub pixel_format: PixelFormat,
@@ -13,6 +20,37 @@ pub struct CompressSettings {
     pub crop: Option<String>,
     pub scale: Option<String>,
     pub other_settings: String,
+
+    pub chapter_split: ChapterSplit,
+    pub chapter_ranges: Option<String>,
+    pub chapter_mapping: ChapterMapping,
+
+    pub stream_rules: StreamRules,
+
+    /// Image that replaces cover art of the outputs, relative paths are resolved against
+    /// the input file directory
+    pub cover: Option<String>,
+    pub container: Container,
+
+    /// None creates "output (n)" in the current folder
+    pub output_folder: Option<String>,
+    pub output_location: OutputLocation,
+    /// None uses `DEFAULT_TEMPLATE`
+    pub output_template: Option<String>,
+    pub existing_output: ExistingOutput,
+
+    pub replace_original: ReplaceOriginal,
+    /// Percent of the original size that the output has to save to replace it
+    pub min_saving: Option<String>,
+    /// Replaced originals are moved to this folder
+    pub trash_folder: Option<String>,
+    /// Replaced originals are renamed with this suffix if there is no trash folder
+    pub backup_suffix: Option<String>,
+
+    /// Copies times, permissions and extended attributes of the inputs to the outputs
+    pub copy_attributes: bool,
+
+    pub skip_rules: SkipRules,
 }
 
 impl CompressSettings {
@@ -36,9 +74,69 @@ impl CompressSettings {
                 self.scale.clone().unwrap_or(String::from("no"))
             ),
             format!("Other settings: {}", self.other_settings),
+            format!("Split by chapters: {}", self.chapter_split),
+            format!(
+                "Chapter ranges: {}",
+                self.chapter_ranges.clone().unwrap_or(String::from("no"))
+            ),
+            format!("Chapters: {}", self.chapter_mapping),
+            format!(
+                "Stream rules: {}",
+                if self.stream_rules.is_empty() {
+                    String::from("no")
+                } else {
+                    self.stream_rules.to_string()
+                }
+            ),
+            format!(
+                "Cover image: {}",
+                self.cover.clone().unwrap_or(String::from("keep source"))
+            ),
+            format!("Container: {}", self.container),
+            format!(
+                "Output folder: {}",
+                self.output_folder.clone().unwrap_or(String::from("auto"))
+            ),
+            format!("Output location: {}", self.output_location),
+            format!(
+                "File name: {}",
+                self.output_template
+                    .clone()
+                    .unwrap_or(String::from(DEFAULT_TEMPLATE))
+            ),
+            format!("If output exists: {}", self.existing_output),
+            format!("Replace original: {}", self.replace_original),
+            format!("Min saving: {}%", self.get_min_saving()),
+            format!(
+                "Trash folder: {}",
+                self.trash_folder.clone().unwrap_or(String::from("no"))
+            ),
+            format!(
+                "Backup suffix: {}",
+                self.backup_suffix.clone().unwrap_or(String::from("no"))
+            ),
+            format!(
+                "Copy file attributes: {}",
+                if self.copy_attributes { "yes" } else { "no" }
+            ),
+            format!(
+                "Skip rules: {}",
+                if self.skip_rules.is_empty() {
+                    String::from("no")
+                } else {
+                    self.skip_rules.to_string()
+                }
+            ),
         ]
     }
 
+    pub fn get_min_saving(&self) -> f64 {
+        self.min_saving
+            .as_deref()
+            .and_then(parse_saving)
+            .unwrap_or(DEFAULT_MIN_SAVING)
+    }
+
     pub fn get_init_arguments(&self) -> Vec<String> {
         let mut result = Vec::new();
         if self.video_codec.is_vaapi() {
@@ -60,6 +158,8 @@ impl CompressSettings {
             self.audio_codec.to_string(),
             "-c:s".to_string(),
             self.subtitle_codec.to_string(),
+            "-c:d".to_string(),
+            "copy".to_string(),
         ]);
 
         if self.audio_codec == AudioCodec::Libopus {
@@ -73,27 +173,6 @@ impl CompressSettings {
             result.extend(vec!["-b:a".to_string(), audio_bitrate]);
         }
 
-        if self.video_codec.is_vaapi() || self.crop.is_some() || self.scale.is_some() {
-            let mut video_format = Vec::new();
-            if let Some(crop) = self.crop.clone() {
-                video_format.push(format!("crop={}", crop));
-            }
-            if self.video_codec.is_vaapi() {
-                video_format.push(format!("format={}", self.pixel_format));
-                video_format.push(String::from("hwupload"));
-                if let Some(scale) = self.scale.clone() {
-                    video_format.push(format!("scale_vaapi={}", scale));
-                }
-            } else {
-                if let Some(scale) = self.scale.clone() {
-                    video_format.push(format!("scale={}", scale));
-                }
-            }
-            if !video_format.is_empty() {
-                result.extend(vec!["-vf".to_string(), video_format.join(",")]);
-            }
-        }
-
         if !self.video_codec.is_vaapi() {
             result.extend(vec!["-pix_fmt".to_string(), self.pixel_format.to_string()]);
         }
@@ -109,20 +188,25 @@ impl CompressSettings {
 
         result
     }
-}
 
-impl Default for CompressSettings {
-    fn default() -> Self {
-        Self {
-            video_codec: VideoCodec::default(),
-            pixel_format: PixelFormat::default(),
-            audio_codec: AudioCodec::default(),
-            subtitle_codec: SubtitleCodec::default(),
-            video_bitrate: None,
-            audio_bitrate: None,
-            crop: None,
-            scale: None,
-            other_settings: String::new(),
+    /// Returns filter chain of the encoded video streams. Attached pictures are copied and
+    /// can't be filtered, so the chain is set per stream.
+    pub fn get_video_filter(&self) -> Option<String> {
+        let mut video_format = Vec::new();
+        if let Some(crop) = self.crop.clone() {
+            video_format.push(format!("crop={}", crop));
+        }
+        if self.video_codec.is_vaapi() {
+            video_format.push(format!("format={}", self.pixel_format));
+            video_format.push(String::from("hwupload"));
+            if let Some(scale) = self.scale.clone() {
+                video_format.push(format!("scale_vaapi={}", scale));
+            }
+        } else {
+            if let Some(scale) = self.scale.clone() {
+                video_format.push(format!("scale={}", scale));
+            }
         }
+        Some(video_format.join(",")).filter(|video_format| !video_format.is_empty())
     }
 }
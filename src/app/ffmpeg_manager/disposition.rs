@@ -0,0 +1,125 @@
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+
+/// ffmpeg names of the disposition flags in `Disposition` order
+pub const DISPOSITION_FLAGS: [&str; 6] = [
+    "default",
+    "forced",
+    "hearing_impaired",
+    "visual_impaired",
+    "comment",
+    "original",
+];
+
+const DISPOSITION_MARKERS: [&str; 6] = ["D", "F", "HI", "VI", "C", "O"];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Disposition {
+    pub default: bool,
+    pub forced: bool,
+    pub hearing_impaired: bool,
+    pub visual_impaired: bool,
+    pub comment: bool,
+    pub original: bool,
+    /// Cover image, set by the stream type instead of the user
+    pub attached_pic: bool,
+}
+
+impl Disposition {
+    pub fn from_json(disposition: &Value) -> Self {
+        let mut result = Self::default();
+        for (i, flag) in DISPOSITION_FLAGS.iter().enumerate() {
+            *result.get_flag_mut(i) = disposition[flag].as_u64().unwrap_or(0) == 1;
+        }
+        result.attached_pic = disposition["attached_pic"].as_u64().unwrap_or(0) == 1;
+        result
+    }
+
+    /// Returns flag by index in `DISPOSITION_FLAGS`
+    pub fn get_flag(&self, index: usize) -> bool {
+        match index {
+            0 => self.default,
+            1 => self.forced,
+            2 => self.hearing_impaired,
+            3 => self.visual_impaired,
+            4 => self.comment,
+            5 => self.original,
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_flag_mut(&mut self, index: usize) -> &mut bool {
+        match index {
+            0 => &mut self.default,
+            1 => &mut self.forced,
+            2 => &mut self.hearing_impaired,
+            3 => &mut self.visual_impaired,
+            4 => &mut self.comment,
+            5 => &mut self.original,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn toggle_flag(&mut self, index: usize) {
+        let flag = self.get_flag_mut(index);
+        *flag = !*flag;
+    }
+
+    /// Returns markers of the set flags: "[D] [F] "
+    pub fn get_markers(&self) -> String {
+        (0..DISPOSITION_FLAGS.len())
+            .filter(|&i| self.get_flag(i))
+            .map(|i| format!("[{}] ", DISPOSITION_MARKERS[i]))
+            .collect()
+    }
+}
+
+/// Value of the ffmpeg -disposition option. "0" clears all flags.
+impl Display for Disposition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut flags: Vec<&str> = (0..DISPOSITION_FLAGS.len())
+            .filter(|&i| self.get_flag(i))
+            .map(|i| DISPOSITION_FLAGS[i])
+            .collect();
+        if self.attached_pic {
+            flags.push("attached_pic");
+        }
+        if flags.is_empty() {
+            f.write_str("0")
+        } else {
+            f.write_str(&flags.join("+"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn formats_disposition_option() {
+        assert_eq!(Disposition::default().to_string(), "0");
+        let disposition = Disposition::from_json(&json!({
+            "default": 1,
+            "forced": 0,
+            "hearing_impaired": 1,
+            "attached_pic": 1,
+        }));
+        assert_eq!(
+            disposition.to_string(),
+            "default+hearing_impaired+attached_pic"
+        );
+        assert_eq!(disposition.get_markers(), "[D] [HI] ");
+    }
+
+    #[test]
+    fn toggles_flags_by_index() {
+        let mut disposition = Disposition::default();
+        disposition.toggle_flag(1);
+        disposition.toggle_flag(5);
+        assert_eq!(disposition.to_string(), "forced+original");
+        disposition.toggle_flag(1);
+        assert_eq!(disposition.to_string(), "original");
+    }
+}
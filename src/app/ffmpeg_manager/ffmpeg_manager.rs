@@ -1,43 +1,132 @@
+use crate::app::ffmpeg_manager::attributes::{copy_times_and_permissions, copy_xattrs};
+use crate::app::ffmpeg_manager::chapter::{
+    Chapter, ChapterMapping, ChapterSegment, get_metadata_path, to_ffmetadata,
+};
+use crate::app::ffmpeg_manager::codec::SubtitleCodec;
 use crate::app::ffmpeg_manager::compress_settings::CompressSettings;
-use serde_json::Value;
+use crate::app::ffmpeg_manager::disposition::Disposition;
+use crate::app::ffmpeg_manager::manifest::{Manifest, OutputState};
+use crate::app::ffmpeg_manager::output::{
+    DEFAULT_TEMPLATE, ExistingOutput, OutputLocation, fill_template, get_date, get_free_path,
+    get_output_folder_name,
+};
+use crate::app::ffmpeg_manager::probe::{
+    FormatData, ProbeData, ProbeError, RejectReason, probe, probe_uncached,
+};
+use crate::app::ffmpeg_manager::probe_pool::ProbePool;
+use crate::app::ffmpeg_manager::replace::{
+    ReplaceOriginal, move_file, move_to_backup, move_to_trash,
+};
+use crate::app::ffmpeg_manager::scan::ScanSettings;
+use crate::app::ffmpeg_manager::sidecar::{
+    find_sidecars, get_sidecar_suffix, parse_sidecar_suffix,
+};
+use crate::app::ffmpeg_manager::verify::{DURATION_TOLERANCE, VerifyError, check_decodable};
+use std::collections::HashSet;
+use std::env;
 use std::fmt::Display;
-use std::io::{Error, ErrorKind};
-use std::path::PathBuf;
-use std::process::Command;
+use std::fs::{metadata, remove_file};
+use std::path::{Path, PathBuf, absolute};
 
-#[derive(Clone, Eq, PartialEq)]
+/// Video stream data. Streams of different files are the same source when their
+/// resolutions match, the rest is probe data of the file.
+#[derive(Clone)]
 pub struct VideoData {
     pub resolution: (u64, u64),
+    pub avg_frame_rate: Option<f64>,
+    pub r_frame_rate: Option<f64>,
+    /// Bitrate in bits per second, often missing in Matroska
+    pub bit_rate: Option<u64>,
+    pub bits_per_raw_sample: Option<u64>,
+    pub sample_aspect_ratio: Option<String>,
+    pub display_aspect_ratio: Option<String>,
+    pub field_order: Option<String>,
 }
 
-#[derive(Clone, Eq, PartialEq)]
+impl PartialEq for VideoData {
+    fn eq(&self, other: &Self) -> bool {
+        self.resolution == other.resolution
+    }
+}
+
+impl Eq for VideoData {}
+
+/// Audio stream data. Streams of different files are the same source when their titles,
+/// channels and languages match, the rest is probe data of the file.
+#[derive(Clone)]
 pub struct AudioData {
     pub title: Option<String>,
     pub channels: u64,
     pub language: Option<String>,
+    pub channel_layout: Option<String>,
+    pub sample_rate: Option<u64>,
+    /// Bitrate in bits per second
+    pub bit_rate: Option<u64>,
+    pub bits_per_raw_sample: Option<u64>,
+}
+
+impl PartialEq for AudioData {
+    fn eq(&self, other: &Self) -> bool {
+        self.title == other.title
+            && self.channels == other.channels
+            && self.language == other.language
+    }
 }
 
+impl Eq for AudioData {}
+
 #[derive(Clone, Eq, PartialEq)]
 pub struct SubtitleData {
     pub title: Option<String>,
     pub language: Option<String>,
 }
 
+#[derive(Clone, Eq, PartialEq)]
+pub struct AttachmentData {
+    pub filename: Option<String>,
+    pub mimetype: Option<String>,
+}
+
+impl AttachmentData {
+    pub fn is_font(&self) -> bool {
+        self.mimetype
+            .as_ref()
+            .is_some_and(|mimetype| mimetype.contains("font"))
+            || self.filename.as_ref().is_some_and(|filename| {
+                let filename = filename.to_ascii_lowercase();
+                FONT_EXTENSIONS
+                    .iter()
+                    .any(|extension| filename.ends_with(extension))
+            })
+    }
+}
+
+const FONT_EXTENSIONS: [&str; 4] = [".ttf", ".otf", ".ttc", ".woff"];
+
+/// Containers that can store attachments
+const ATTACHMENT_CONTAINERS: [&str; 3] = ["mkv", "mka", "mks"];
+
 #[derive(Clone, Eq, PartialEq)]
 pub enum StreamType {
     Video(VideoData),
     Audio(AudioData),
     Subtitle(SubtitleData),
-    Attachment,
+    Attachment(AttachmentData),
+    /// Cover art, a video stream with attached_pic disposition
+    Picture(VideoData),
+    /// Data streams like timecode tracks, dropped by default
+    Data,
 }
 
 impl StreamType {
+    /// Returns index of the ffmpeg stream type, attached pictures are video streams
     pub fn to_index(&self) -> usize {
         match self {
-            StreamType::Video(_) => 0,
+            StreamType::Video(_) | StreamType::Picture(_) => 0,
             StreamType::Audio(_) => 1,
             StreamType::Subtitle(_) => 2,
-            StreamType::Attachment => 3,
+            StreamType::Attachment(_) => 3,
+            StreamType::Data => 4,
         }
     }
 }
@@ -45,33 +134,122 @@ impl StreamType {
 impl Display for StreamType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.write_str(match self {
-            StreamType::Video(_) => "v",
+            StreamType::Video(_) | StreamType::Picture(_) => "v",
             StreamType::Audio(_) => "a",
             StreamType::Subtitle(_) => "s",
-            StreamType::Attachment => "t",
+            StreamType::Attachment(_) => "t",
+            StreamType::Data => "d",
         })
     }
 }
 
-#[derive(Clone, Eq, PartialEq)]
+/// Stream of the input file or its sidecar. Streams of different files are the same source
+/// when everything but the index matches.
+#[derive(Clone)]
 pub struct Stream {
+    /// ffprobe index of the stream in its file
+    pub index: u64,
     pub stream_type: StreamType,
     pub codec_name: String,
-    pub default: bool,
+    pub disposition: Disposition,
+    /// Suffix of the sidecar file the stream comes from ("rus.srt"), None for the input file
+    pub origin: Option<String>,
+}
+
+impl PartialEq for Stream {
+    fn eq(&self, other: &Self) -> bool {
+        self.stream_type == other.stream_type
+            && self.codec_name == other.codec_name
+            && self.disposition == other.disposition
+            && self.origin == other.origin
+    }
+}
+
+impl Eq for Stream {}
+
+impl Stream {
+    pub fn get_title(&self) -> Option<&String> {
+        match &self.stream_type {
+            StreamType::Audio(audio_data) => audio_data.title.as_ref(),
+            StreamType::Subtitle(subtitle_data) => subtitle_data.title.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn get_language(&self) -> Option<&String> {
+        match &self.stream_type {
+            StreamType::Audio(audio_data) => audio_data.language.as_ref(),
+            StreamType::Subtitle(subtitle_data) => subtitle_data.language.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl Stream {
+    /// Returns lines for the details pane of the file stream
+    pub fn get_details(&self) -> Vec<String> {
+        let mut result = vec![format!("Codec: {}", self.codec_name)];
+        match &self.stream_type {
+            StreamType::Video(video_data) | StreamType::Picture(video_data) => {
+                result.push(format!(
+                    "Resolution: {}x{}",
+                    video_data.resolution.0, video_data.resolution.1
+                ));
+                if let Some(avg_frame_rate) = video_data.avg_frame_rate {
+                    result.push(format!("Frame rate: {:.3} fps", avg_frame_rate));
+                }
+                if let Some(r_frame_rate) = video_data.r_frame_rate
+                    && video_data.avg_frame_rate != Some(r_frame_rate)
+                {
+                    result.push(format!("Base frame rate: {:.3} fps", r_frame_rate));
+                }
+                if let Some(bit_rate) = video_data.bit_rate {
+                    result.push(format!("Bitrate: {} kb/s", bit_rate / 1000));
+                }
+                if let Some(bits_per_raw_sample) = video_data.bits_per_raw_sample {
+                    result.push(format!("Bit depth: {}", bits_per_raw_sample));
+                }
+                if let Some(sample_aspect_ratio) = &video_data.sample_aspect_ratio {
+                    result.push(format!("SAR: {}", sample_aspect_ratio));
+                }
+                if let Some(display_aspect_ratio) = &video_data.display_aspect_ratio {
+                    result.push(format!("DAR: {}", display_aspect_ratio));
+                }
+                if let Some(field_order) = &video_data.field_order {
+                    result.push(format!("Field order: {}", field_order));
+                }
+            }
+            StreamType::Audio(audio_data) => {
+                result.push(format!(
+                    "Channels: {} ({})",
+                    audio_data.channels,
+                    audio_data.channel_layout.as_deref().unwrap_or("unknown")
+                ));
+                if let Some(sample_rate) = audio_data.sample_rate {
+                    result.push(format!("Sample rate: {} Hz", sample_rate));
+                }
+                if let Some(bit_rate) = audio_data.bit_rate {
+                    result.push(format!("Bitrate: {} kb/s", bit_rate / 1000));
+                }
+                if let Some(bits_per_raw_sample) = audio_data.bits_per_raw_sample {
+                    result.push(format!("Bit depth: {}", bits_per_raw_sample));
+                }
+            }
+            _ => {}
+        }
+        result
+    }
 }
 
 impl Display for Stream {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let str = match &self.stream_type {
-            StreamType::Video(video_data) => String::from(format!(
+            StreamType::Video(video_data) => format!(
                 "Video {} ({}:{})",
                 self.codec_name, video_data.resolution.0, video_data.resolution.1
-            )),
+            ),
             StreamType::Audio(audio_data) => {
-                let mut result = String::from(format!(
-                    "Audio {} ({} ch)",
-                    self.codec_name, audio_data.channels
-                ));
+                let mut result = format!("Audio {} ({} ch)", self.codec_name, audio_data.channels);
                 if let Some(title) = &audio_data.title {
                     result += &format!(" {}", title);
                 }
@@ -81,7 +259,7 @@ impl Display for Stream {
                 result
             }
             StreamType::Subtitle(subtitle_data) => {
-                let mut result = String::from(format!("Subtitle {}", self.codec_name));
+                let mut result = format!("Subtitle {}", self.codec_name);
                 if let Some(title) = &subtitle_data.title {
                     result += &format!(" {}", title);
                 }
@@ -90,86 +268,138 @@ impl Display for Stream {
                 }
                 result
             }
-            StreamType::Attachment => String::from("Attachment"),
+            StreamType::Attachment(attachment_data) => {
+                let mut result = String::from("Attachment");
+                if let Some(filename) = &attachment_data.filename {
+                    result += &format!(" {}", filename);
+                }
+                if let Some(mimetype) = &attachment_data.mimetype {
+                    result += &format!(" ({})", mimetype);
+                }
+                result
+            }
+            StreamType::Picture(video_data) => format!(
+                "Cover {} ({}:{})",
+                self.codec_name, video_data.resolution.0, video_data.resolution.1
+            ),
+            StreamType::Data => format!("Data {}", self.codec_name),
         };
-        write!(f, "{}", str)
+        write!(f, "{}", str)?;
+        if let Some(origin) = &self.origin {
+            write!(f, " [external {}]", origin)?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-file override of the stream settings, None keeps the global value
+#[derive(Clone, Default)]
+pub struct StreamOverride {
+    pub enabled: Option<bool>,
+    pub default: Option<bool>,
+}
+
+impl StreamOverride {
+    pub fn is_empty(&self) -> bool {
+        self.enabled.is_none() && self.default.is_none()
     }
 }
 
 pub struct InputFile {
     pub path: PathBuf,
     pub sources: Vec<Stream>,
+    pub chapters: Vec<Chapter>,
+    pub format: FormatData,
+    pub sidecars: Vec<PathBuf>,
+    /// Overrides for each of `sources`
+    pub overrides: Vec<StreamOverride>,
+    /// Reasons of streams and sidecars that are left out
+    pub probe_errors: Vec<ProbeError>,
+    /// Skips (true) or compresses (false) the file regardless of the skip rules
+    pub skip_override: Option<bool>,
 }
 
 impl InputFile {
-    fn from_path(path: PathBuf) -> Self {
-        let sources = Self::process_path(&path).unwrap_or(Vec::new());
-        Self { path, sources }
-    }
-
-    fn process_path(path: &PathBuf) -> Result<Vec<Stream>, Error> {
-        if !path.exists() {
-            return Err(ErrorKind::NotFound.into());
-        }
-        let output = Command::new("ffprobe")
-            .arg("-show_streams")
-            .arg("-output_format")
-            .arg("json")
-            .arg(path.to_str().expect("Failed to convert path to string"))
-            .output()?;
-        if !output.status.success() {
-            return Err(Error::new(
-                ErrorKind::Other,
-                "ffprobe exited with non-zero status code",
-            ));
-        }
-
-        let json_string =
-            String::from_utf8(output.stdout).expect("Failed to convert output to string");
-        let json_data: Value = serde_json::from_str(&json_string)?;
-
-        let mut result = vec![];
-        for stream in json_data["streams"].as_array().unwrap() {
-            let new_stream = Stream {
-                stream_type: match stream["codec_type"].as_str().unwrap() {
-                    "video" => StreamType::Video(VideoData {
-                        resolution: (
-                            stream["width"].as_u64().unwrap(),
-                            stream["height"].as_u64().unwrap(),
-                        ),
-                    }),
-                    "audio" => StreamType::Audio(AudioData {
-                        title: match stream["tags"]["title"].as_str() {
-                            Some(title) => Some(title.to_string()),
-                            None => None,
-                        },
-                        channels: stream["channels"].as_u64().unwrap(),
-                        language: match stream["tags"]["language"].as_str() {
-                            Some(language) => Some(language.to_string()),
-                            None => None,
-                        },
-                    }),
-                    "subtitle" => StreamType::Subtitle(SubtitleData {
-                        title: match stream["tags"]["title"].as_str() {
-                            Some(title) => Some(title.to_string()),
-                            None => None,
-                        },
-                        language: match stream["tags"]["language"].as_str() {
-                            Some(language) => Some(language.to_string()),
-                            None => None,
-                        },
-                    }),
-                    "attachment" => StreamType::Attachment,
-                    &_ => {
+    pub fn from_path(path: PathBuf) -> Result<Self, ProbeError> {
+        let ProbeData {
+            streams: mut sources,
+            chapters,
+            format,
+            errors: mut probe_errors,
+        } = probe(&path)?;
+        let mut sidecars = Vec::new();
+        if !sources.is_empty() {
+            for sidecar in find_sidecars(&path) {
+                let Some(suffix) = get_sidecar_suffix(&sidecar, &path) else {
+                    continue;
+                };
+                let sidecar_sources = match probe(&sidecar) {
+                    Ok(sidecar_data) => sidecar_data.streams,
+                    Err(error) => {
+                        probe_errors.push(ProbeError::Sidecar {
+                            suffix,
+                            error: Box::new(error),
+                        });
                         continue;
                     }
-                },
-                codec_name: stream["codec_name"].as_str().unwrap().to_string(),
-                default: stream["disposition"]["default"].as_u64().unwrap_or(0) == 1,
-            };
-            result.push(new_stream);
+                };
+                let (language, title) = parse_sidecar_suffix(&suffix);
+                for mut source in sidecar_sources {
+                    match &mut source.stream_type {
+                        StreamType::Audio(AudioData {
+                            title: stream_title,
+                            language: stream_language,
+                            ..
+                        })
+                        | StreamType::Subtitle(SubtitleData {
+                            title: stream_title,
+                            language: stream_language,
+                        }) => {
+                            if language.is_some() {
+                                *stream_language = language.clone();
+                            }
+                            if title.is_some() {
+                                *stream_title = title.clone();
+                            }
+                        }
+                        _ => continue,
+                    }
+                    source.origin = Some(suffix.clone());
+                    sources.push(source);
+                }
+                sidecars.push(sidecar);
+            }
+        }
+        Ok(Self {
+            path,
+            overrides: vec![StreamOverride::default(); sources.len()],
+            sources,
+            chapters,
+            format,
+            sidecars,
+            probe_errors,
+            skip_override: None,
+        })
+    }
+
+    pub fn has_overrides(&self) -> bool {
+        self.overrides.iter().any(|o| !o.is_empty())
+    }
+
+    /// Returns index of the ffmpeg input that contains the stream
+    pub fn get_input_index(&self, stream: &Stream) -> usize {
+        match &stream.origin {
+            None => 0,
+            Some(origin) => {
+                self.sidecars
+                    .iter()
+                    .position(|sidecar| {
+                        get_sidecar_suffix(sidecar, &self.path).as_ref() == Some(origin)
+                    })
+                    .unwrap()
+                    + 1
+            }
         }
-        Ok(result)
     }
 }
 
@@ -194,19 +424,27 @@ pub struct FfmpegStreamSettings {
     pub stream: Stream,
     pub files: FfmpegStreamFiles,
     pub enabled: bool,
-    pub default: bool,
+    pub disposition: Disposition,
+    /// Output title, None keeps the source title, empty string removes it
+    pub title: Option<String>,
+    /// Output ISO 639-2 language, None keeps the source language
+    pub language: Option<String>,
 }
 
 impl FfmpegStreamSettings {
     pub fn toggle_enabled(&mut self) {
         self.enabled = !self.enabled;
         if !self.enabled {
-            self.default = false;
+            self.disposition.default = false;
         }
     }
     pub fn toggle_default(&mut self) {
+        self.toggle_disposition(0);
+    }
+    /// Toggles flag by index in `DISPOSITION_FLAGS`
+    pub fn toggle_disposition(&mut self, index: usize) {
         if self.enabled {
-            self.default = !self.default;
+            self.disposition.toggle_flag(index);
         }
     }
 }
@@ -215,42 +453,131 @@ impl Display for FfmpegStreamSettings {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut result = String::new();
         result += if self.enabled { "[X] " } else { "[ ] " };
-        if self.default {
-            result += "[D] ";
-        }
+        result += &self.disposition.get_markers();
         result += &self.stream.to_string();
         result += &self.files.to_string();
+        if let Some(title) = &self.title {
+            result += &format!(" [title: {}]", title);
+        }
+        if let Some(language) = &self.language {
+            result += &format!(" [language: {}]", language);
+        }
         write!(f, "{}", result)
     }
 }
 
+/// File that can't be added, with the error of its probe
+pub struct RejectedFile {
+    pub path: PathBuf,
+    pub error: ProbeError,
+}
+
+impl RejectedFile {
+    pub fn get_reason(&self) -> RejectReason {
+        self.error.get_reject_reason()
+    }
+}
+
+#[derive(Default)]
 pub struct FfmpegManager {
     pub input_files: Vec<InputFile>,
+    pub rejected_files: Vec<RejectedFile>,
     pub stream_settings: Vec<FfmpegStreamSettings>,
     pub compress_settings: CompressSettings,
+    pub probe_pool: ProbePool,
+    pub scan_settings: ScanSettings,
+    /// Scanned folders, their structure is recreated in the output folder
+    pub input_roots: Vec<PathBuf>,
+    /// Manifest of the unfinished batch
+    pub manifest: Option<Manifest>,
+    /// Queued files found by a folder scan, only they are checked for samples
+    scanned_paths: HashSet<PathBuf>,
 }
 
 impl FfmpegManager {
+    /// Queues the file for probing, it is added by `receive_probe_results`
     pub fn add_file(&mut self, path: PathBuf) {
-        let input_file = InputFile::from_path(path);
-        if !input_file.sources.is_empty() {
-            self.input_files.push(input_file);
+        self.probe_pool.push(path);
+    }
+
+    /// Adds files probed since the last call. Returns true if anything was added.
+    pub fn receive_probe_results(&mut self) -> bool {
+        let probe_results = self.probe_pool.receive();
+        if probe_results.is_empty() {
+            return false;
+        }
+        let input_files_count = self.input_files.len();
+        for (path, probe_result) in probe_results {
+            let is_scanned = self.scanned_paths.remove(&path);
+            match probe_result {
+                Ok(input_file)
+                    if is_scanned
+                        && self
+                            .scan_settings
+                            .is_sample(&input_file.path, input_file.format.duration) =>
+                {
+                    self.rejected_files.push(RejectedFile {
+                        path,
+                        error: ProbeError::Sample(input_file.format.duration.unwrap_or_default()),
+                    })
+                }
+                Ok(input_file) if !input_file.sources.is_empty() => {
+                    // Probes finish in any order, the list is kept sorted to stay the same
+                    // between runs
+                    let index = self
+                        .input_files
+                        .partition_point(|other| other.path <= input_file.path);
+                    self.input_files.insert(index, input_file);
+                }
+                Ok(mut input_file) => self.rejected_files.push(RejectedFile {
+                    path,
+                    error: input_file.probe_errors.remove(0),
+                }),
+                Err(error) => self.rejected_files.push(RejectedFile { path, error }),
+            }
+        }
+        if self.input_files.len() != input_files_count {
             self.update_stream_settings();
         }
+        true
+    }
+
+    /// Probes the rejected file again, it is added to input files by path or to the end of
+    /// rejected files when the probe finishes. A retried sample is added as a normal file.
+    pub fn retry_rejected_file(&mut self, rejected_file_index: usize) {
+        let rejected_file = self.rejected_files.remove(rejected_file_index);
+        self.add_file(rejected_file.path);
     }
 
+    /// Adds files of the folder and its subfolders that pass the scan settings
     pub fn add_folder(&mut self, path: PathBuf) {
         if !path.is_dir() {
             return;
         }
-        let mut paths: Vec<_> = path
-            .read_dir()
-            .unwrap()
-            .map(|entry| entry.unwrap().path())
-            .collect();
-        paths.sort();
-        for path in paths {
-            self.add_file(path);
+        let (mut paths, errors) = self.scan_settings.scan(&path);
+        // Outputs of the earlier runs are not compressed again
+        if let Some(output_folder) = &self.compress_settings.output_folder
+            && let Ok(output_folder) = absolute(output_folder)
+        {
+            paths.retain(|path| absolute(path).is_ok_and(|path| !path.starts_with(&output_folder)));
+        }
+        self.input_roots.push(path);
+        for (path, error) in errors {
+            self.rejected_files.push(RejectedFile {
+                path,
+                error: ProbeError::Io(error),
+            });
+        }
+        for path in paths.iter() {
+            // Sidecar files are added together with their main file
+            if paths
+                .iter()
+                .any(|main| get_sidecar_suffix(path, main).is_some())
+            {
+                continue;
+            }
+            self.scanned_paths.insert(path.clone());
+            self.add_file(path.clone());
         }
     }
 
@@ -263,18 +590,37 @@ impl FfmpegManager {
             }
         }
     }
+    /// Rebuilds stream settings from input files. Settings and order of streams that are
+    /// still present are kept, new streams are appended.
     pub fn update_stream_settings(&mut self) {
-        self.stream_settings.clear();
+        let old_stream_settings = std::mem::take(&mut self.stream_settings);
         let mut sources = Vec::new();
+        for mut stream_settings in old_stream_settings {
+            if self
+                .input_files
+                .iter()
+                .any(|input_file| input_file.sources.contains(&stream_settings.stream))
+            {
+                stream_settings.files = FfmpegStreamFiles::All;
+                sources.push(stream_settings.stream.clone());
+                self.stream_settings.push(stream_settings);
+            }
+        }
         for input_file in self.input_files.iter() {
             for source in input_file.sources.iter() {
                 if !sources.contains(source) {
-                    self.stream_settings.push(FfmpegStreamSettings {
+                    let mut stream_settings = FfmpegStreamSettings {
                         stream: source.clone(),
                         files: FfmpegStreamFiles::All,
-                        enabled: true,
-                        default: source.default,
-                    });
+                        enabled: source.stream_type != StreamType::Data,
+                        disposition: source.disposition,
+                        title: None,
+                        language: None,
+                    };
+                    self.compress_settings
+                        .stream_rules
+                        .apply(&mut stream_settings);
+                    self.stream_settings.push(stream_settings);
                     sources.push(source.clone());
                 }
             }
@@ -292,67 +638,861 @@ impl FfmpegManager {
         }
     }
 
+    /// Evaluates stream rules against all streams again
+    pub fn apply_stream_rules(&mut self) {
+        for stream_settings in &mut self.stream_settings {
+            stream_settings.enabled = stream_settings.stream.stream_type != StreamType::Data;
+            stream_settings.disposition = stream_settings.stream.disposition;
+            self.compress_settings.stream_rules.apply(stream_settings);
+        }
+    }
+
+    /// Moves stream settings up (-1) or down (+1) in the output order
+    pub fn move_stream_settings(&mut self, stream_settings_index: usize, offset: isize) {
+        let Some(new_index) = stream_settings_index.checked_add_signed(offset) else {
+            return;
+        };
+        if new_index < self.stream_settings.len() {
+            self.stream_settings.swap(stream_settings_index, new_index);
+        }
+    }
+
+    fn get_settings_index(&self, stream: &Stream) -> usize {
+        self.stream_settings
+            .iter()
+            .position(|ss| ss.stream == *stream)
+            .unwrap()
+    }
+
+    fn get_settings(&self, stream: &Stream) -> &FfmpegStreamSettings {
+        self.stream_settings
+            .iter()
+            .find(|ss| ss.stream == *stream)
+            .unwrap()
+    }
+
+    pub fn set_title(&mut self, stream_settings_index: usize, title: String) {
+        let stream_settings = &mut self.stream_settings[stream_settings_index];
+        stream_settings.title = if stream_settings.stream.get_title() == Some(&title) {
+            None
+        } else {
+            Some(title)
+        };
+    }
+
+    pub fn set_language(&mut self, stream_settings_index: usize, language: Option<String>) {
+        let stream_settings = &mut self.stream_settings[stream_settings_index];
+        stream_settings.language =
+            language.filter(|language| stream_settings.stream.get_language() != Some(language));
+    }
+
+    /// Returns enabled and disposition of the file source with per-file overrides applied
+    pub fn get_source_state(
+        &self,
+        input_file: &InputFile,
+        source_index: usize,
+    ) -> (bool, Disposition) {
+        let settings = self.get_settings(&input_file.sources[source_index]);
+        let source_override = &input_file.overrides[source_index];
+        let enabled = source_override.enabled.unwrap_or(settings.enabled);
+        let mut disposition = settings.disposition;
+        disposition.default = enabled
+            && source_override
+                .default
+                .unwrap_or(settings.disposition.default);
+        (enabled, disposition)
+    }
+
+    pub fn get_source_description(&self, input_file: &InputFile, source_index: usize) -> String {
+        let (enabled, disposition) = self.get_source_state(input_file, source_index);
+        let mut result = String::new();
+        result += if enabled { "[X] " } else { "[ ] " };
+        result += &disposition.get_markers();
+        result += &input_file.sources[source_index].to_string();
+        if !input_file.overrides[source_index].is_empty() {
+            result += " *";
+        }
+        result
+    }
+
+    pub fn toggle_source_enabled(&mut self, file_index: usize, source_index: usize) {
+        let input_file = &self.input_files[file_index];
+        let (enabled, _) = self.get_source_state(input_file, source_index);
+        let settings_enabled = self.get_settings(&input_file.sources[source_index]).enabled;
+        self.input_files[file_index].overrides[source_index].enabled =
+            Some(!enabled).filter(|&enabled| enabled != settings_enabled);
+    }
+
+    pub fn toggle_source_default(&mut self, file_index: usize, source_index: usize) {
+        let input_file = &self.input_files[file_index];
+        let (enabled, disposition) = self.get_source_state(input_file, source_index);
+        if !enabled {
+            return;
+        }
+        let default = disposition.default;
+        let settings_default = self
+            .get_settings(&input_file.sources[source_index])
+            .disposition
+            .default;
+        self.input_files[file_index].overrides[source_index].default =
+            Some(!default).filter(|&default| default != settings_default);
+    }
+
+    pub fn reset_source_override(&mut self, file_index: usize, source_index: usize) {
+        self.input_files[file_index].overrides[source_index] = StreamOverride::default();
+    }
+
+    /// Returns numbers of files that override the stream settings
+    pub fn get_overriding_files(&self, stream_settings: &FfmpegStreamSettings) -> Vec<usize> {
+        self.input_files
+            .iter()
+            .enumerate()
+            .filter(|(_, input_file)| {
+                input_file
+                    .sources
+                    .iter()
+                    .zip(input_file.overrides.iter())
+                    .any(|(source, o)| *source == stream_settings.stream && !o.is_empty())
+            })
+            .map(|(i, _)| i + 1)
+            .collect()
+    }
+
     pub fn get_command_template(&self) -> String {
         let mut result = vec!["ffmpeg".to_string()];
         result.extend(self.compress_settings.get_init_arguments());
         result.push("<input file> <streams>".to_string());
         result.extend(self.compress_settings.get_compress_arguments());
+        // Same form as `get_command_video_codecs`: filters are set per video stream and
+        // cover pictures are copied
+        if let Some(video_filter) = self.compress_settings.get_video_filter() {
+            result.extend(vec!["-filter:v:<video>".to_string(), video_filter]);
+        }
+        result.push("[-c:v:<cover> copy]".to_string());
         result.push("<output file>".to_string());
         result.join(" ")
     }
 
-    fn get_command_streams(&self, input_file: &InputFile) -> Vec<String> {
+    /// Returns folder of the file relative to the scanned folder it was found in, empty for
+    /// files that were added directly
+    fn get_relative_folder(&self, input_file: &InputFile) -> PathBuf {
+        let parent = input_file.path.parent().unwrap_or(Path::new(""));
+        self.input_roots
+            .iter()
+            .filter_map(|root| parent.strip_prefix(root).ok())
+            .min_by_key(|relative_folder| relative_folder.components().count())
+            .map(|relative_folder| relative_folder.to_path_buf())
+            .unwrap_or_default()
+    }
+
+    /// Returns the reason the file is not compressed, the override of the file goes first
+    pub fn get_skip_reason(&self, input_file: &InputFile) -> Option<String> {
+        match input_file.skip_override {
+            Some(false) => None,
+            Some(true) => Some(String::from("skipped manually")),
+            None => self
+                .compress_settings
+                .skip_rules
+                .get_skip_reason(input_file),
+        }
+    }
+
+    /// Overrides the skip rules for the file, toggling it back removes the override
+    pub fn toggle_skip(&mut self, file_index: usize) {
+        let input_file = &self.input_files[file_index];
+        let skipped_by_rules = self
+            .compress_settings
+            .skip_rules
+            .get_skip_reason(input_file)
+            .is_some();
+        let skipped = self.get_skip_reason(input_file).is_some();
+        self.input_files[file_index].skip_override = if skipped == skipped_by_rules {
+            Some(!skipped)
+        } else {
+            None
+        };
+    }
+
+    /// Returns the folder that the outputs are written to: the chosen one, the folder of
+    /// the unfinished batch of these files or the first "output (n)" that doesn't exist in
+    /// the current folder
+    pub fn get_output_root(&self) -> PathBuf {
+        if let Some(output_folder) = &self.compress_settings.output_folder {
+            return PathBuf::from(output_folder);
+        }
+        if let Some(manifest) = &self.manifest
+            && let Some(output_root) = &manifest.output_root
+            && output_root.exists()
+            && self
+                .input_files
+                .iter()
+                .any(|input_file| manifest.contains_input(&input_file.path))
+        {
+            return output_root.clone();
+        }
+        let mut index = 0usize;
+        loop {
+            let mut result = env::current_dir().unwrap_or_default();
+            result.push(get_output_folder_name(index));
+            if !result.exists() {
+                return result;
+            }
+            index += 1;
+        }
+    }
+
+    /// Returns height of the first video stream of the output, scale is taken into account
+    fn get_output_height(&self, input_file: &InputFile) -> Option<u64> {
+        let (width, height) = input_file
+            .sources
+            .iter()
+            .enumerate()
+            .find_map(|(i, source)| match &source.stream_type {
+                StreamType::Video(video_data) if self.get_source_state(input_file, i).0 => {
+                    Some(video_data.resolution)
+                }
+                _ => None,
+            })?;
+        let Some((scale_width, scale_height)) = self
+            .compress_settings
+            .scale
+            .as_ref()
+            .and_then(|scale| scale.split_once([':', 'x']))
+        else {
+            return Some(height);
+        };
+        match (scale_width.parse::<i64>(), scale_height.parse::<i64>()) {
+            (_, Ok(scale_height)) if scale_height > 0 => Some(scale_height as u64),
+            (Ok(scale_width), _) if scale_width > 0 && width > 0 => {
+                Some((scale_width as u64 * height).div_ceil(width))
+            }
+            _ => Some(height),
+        }
+    }
+
+    /// Returns output paths of the file in the output root with the segments they contain
+    pub fn get_outputs(
+        &self,
+        input_file: &InputFile,
+        output_root: &Path,
+    ) -> Vec<(PathBuf, Option<ChapterSegment>)> {
+        let folder = match self.compress_settings.output_location {
+            OutputLocation::OutputFolder => output_root.join(self.get_relative_folder(input_file)),
+            OutputLocation::NextToSource => input_file
+                .path
+                .parent()
+                .unwrap_or(Path::new(""))
+                .to_path_buf(),
+        };
+        let template = self
+            .compress_settings
+            .output_template
+            .as_deref()
+            .unwrap_or(DEFAULT_TEMPLATE);
+        let codec = match self.compress_settings.video_codec.get_format_name() {
+            Some(codec) => codec.to_string(),
+            None => input_file
+                .sources
+                .iter()
+                .find(|source| matches!(source.stream_type, StreamType::Video(_)))
+                .map(|source| source.codec_name.clone())
+                .unwrap_or_default(),
+        };
+        let height = self
+            .get_output_height(input_file)
+            .map(|height| height.to_string())
+            .unwrap_or_default();
+        let extension = self.get_output_extension(input_file).unwrap_or_default();
+        let date = get_date();
+        let get_output_file = |stem: String| {
+            let values = [
+                ("stem", stem),
+                ("codec", codec.clone()),
+                ("height", height.clone()),
+                ("ext", extension.clone()),
+                ("date", date.clone()),
+            ];
+            folder.join(fill_template(template, &values).trim_end_matches('.'))
+        };
+
+        // Files with invalid ranges are refused by run_compressing
+        let segments = self.get_segments(input_file).unwrap_or_default();
+        if segments.is_empty() {
+            let stem = input_file.path.file_stem().unwrap_or_default();
+            return vec![(get_output_file(stem.to_string_lossy().to_string()), None)];
+        }
+        segments
+            .into_iter()
+            .map(|segment| (get_output_file(segment.name.clone()), Some(segment)))
+            .collect()
+    }
+
+    /// Returns output paths that more than one output is written to or that are input files
+    pub fn get_output_collisions(&self, output_root: &Path) -> Vec<PathBuf> {
+        let mut output_paths: HashSet<PathBuf> = self
+            .input_files
+            .iter()
+            .map(|input_file| input_file.path.clone())
+            .collect();
         let mut result = Vec::new();
-        let mut source_indexes = [0; 4];
-        input_file.sources.iter().for_each(|source| {
-            let stream_setting = self
-                .stream_settings
+        for input_file in &self.input_files {
+            for (output_path, _) in self.get_outputs(input_file, output_root) {
+                if !output_paths.insert(output_path.clone()) && !result.contains(&output_path) {
+                    result.push(output_path);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns true if the existing output is complete: it was finished by the last batch
+    /// and not modified since, or it is verified
+    fn is_output_valid(
+        &self,
+        input_file: &InputFile,
+        output_path: &Path,
+        segment: Option<&ChapterSegment>,
+    ) -> bool {
+        if let Some(entry) = self
+            .manifest
+            .as_ref()
+            .and_then(|manifest| manifest.get_entry(output_path))
+        {
+            return entry.state == OutputState::Done
+                && entry.size.is_some()
+                && entry.size == metadata(output_path).ok().map(|metadata| metadata.len());
+        }
+        self.verify_output(input_file, output_path, segment).is_ok()
+    }
+
+    /// Checks the written file against the input: duration, number of streams of each type
+    /// and decoding of its start and end
+    pub fn verify_output(
+        &self,
+        input_file: &InputFile,
+        output_path: &Path,
+        segment: Option<&ChapterSegment>,
+    ) -> Result<(), VerifyError> {
+        let probe_data = probe_uncached(output_path).map_err(VerifyError::Probe)?;
+        let expected_duration = match segment {
+            Some(segment) => Some(segment.end - segment.start),
+            None => input_file.format.duration,
+        };
+        if let Some(expected_duration) = expected_duration
+            && probe_data
+                .format
+                .duration
+                .is_none_or(|duration| (expected_duration - duration).abs() > DURATION_TOLERANCE)
+        {
+            return Err(VerifyError::Duration {
+                expected: expected_duration,
+                actual: probe_data.format.duration,
+            });
+        }
+
+        let mut expected_counts = [0; 5];
+        for (i, _) in self.get_output_sources(input_file) {
+            expected_counts[input_file.sources[i].stream_type.to_index()] += 1;
+        }
+        if self.get_cover_path(input_file).is_some() {
+            expected_counts[0] += 1;
+        }
+        let mut counts = [0; 5];
+        for stream in &probe_data.streams {
+            counts[stream.stream_type.to_index()] += 1;
+        }
+        if let Some(stream_type) = (0..5).find(|&i| counts[i] != expected_counts[i]) {
+            return Err(VerifyError::StreamCount {
+                stream_type,
+                expected: expected_counts[stream_type],
+                actual: counts[stream_type],
+            });
+        }
+
+        check_decodable(output_path, false)?;
+        check_decodable(output_path, true)
+    }
+
+    /// Applies the existing output policy. Returns the path to write the output to or
+    /// None if the output is skipped.
+    pub fn get_output_path(
+        &self,
+        input_file: &InputFile,
+        output_path: &Path,
+        segment: Option<&ChapterSegment>,
+    ) -> Option<PathBuf> {
+        if !output_path.exists() {
+            return Some(output_path.to_path_buf());
+        }
+        match self.compress_settings.existing_output {
+            ExistingOutput::Skip => None,
+            ExistingOutput::Overwrite => Some(output_path.to_path_buf()),
+            ExistingOutput::Rename => Some(get_free_path(output_path)),
+            ExistingOutput::SkipIfValid => Some(output_path.to_path_buf())
+                .filter(|_| !self.is_output_valid(input_file, output_path, segment)),
+        }
+    }
+
+    /// Replaces the input file with its verified output if the output is smaller by the
+    /// minimal saving. The original is moved to the trash folder, renamed with the backup
+    /// suffix or deleted. Returns the report of the replacement or the reason the original
+    /// is kept.
+    pub fn replace_original(
+        &self,
+        input_file: &InputFile,
+        output_path: &Path,
+    ) -> Result<String, String> {
+        let source_metadata = metadata(&input_file.path).map_err(|error| error.to_string())?;
+        let output_size = metadata(output_path)
+            .map_err(|error| error.to_string())?
+            .len();
+        let saving = 100.0 - output_size as f64 * 100.0 / source_metadata.len().max(1) as f64;
+        let min_saving = self.compress_settings.get_min_saving();
+        if saving < min_saving {
+            return Err(format!(
+                "saves {:.1}%, less than {}%",
+                saving.max(0.0),
+                min_saving
+            ));
+        }
+        // The output keeps its extension, so the container matches the name
+        let mut target_path = input_file.path.clone();
+        if let Some(extension) = output_path.extension() {
+            target_path.set_extension(extension);
+        }
+        // Next to the source the output can already have the target name
+        if target_path != input_file.path && target_path != output_path && target_path.exists() {
+            return Err(format!("{} exists", target_path.display()));
+        }
+        if self.compress_settings.replace_original == ReplaceOriginal::DryRun {
+            return Ok(format!("would replace, saves {:.1}%", saving));
+        }
+
+        copy_xattrs(&input_file.path, output_path).map_err(|error| error.to_string())?;
+        let backup_path = if let Some(trash_folder) = &self.compress_settings.trash_folder {
+            Some(
+                move_to_trash(&input_file.path, Path::new(trash_folder))
+                    .map_err(|error| error.to_string())?,
+            )
+        } else if let Some(backup_suffix) = &self.compress_settings.backup_suffix {
+            Some(
+                move_to_backup(&input_file.path, backup_suffix)
+                    .map_err(|error| error.to_string())?,
+            )
+        } else {
+            None
+        };
+        if let Err(error) = move_file(output_path, &target_path) {
+            // The original is restored, so nothing is lost
+            if let Some(backup_path) = &backup_path {
+                let _ = move_file(backup_path, &input_file.path);
+            }
+            return Err(error.to_string());
+        }
+        if backup_path.is_none() && target_path != input_file.path {
+            remove_file(&input_file.path).map_err(|error| error.to_string())?;
+        }
+        let mut result = match backup_path {
+            Some(backup_path) => format!(
+                "saves {:.1}%, original moved to {}",
+                saving,
+                backup_path.display()
+            ),
+            None => format!("saves {:.1}%, original deleted", saving),
+        };
+        // Streams of the sidecars are in the output now, the next scan would add them again
+        for sidecar in &input_file.sidecars {
+            let moved = if let Some(trash_folder) = &self.compress_settings.trash_folder {
+                move_to_trash(sidecar, Path::new(trash_folder)).map(|_| ())
+            } else if let Some(backup_suffix) = &self.compress_settings.backup_suffix {
+                move_to_backup(sidecar, backup_suffix).map(|_| ())
+            } else {
+                remove_file(sidecar)
+            };
+            if let Err(error) = moved {
+                result += &format!(", {} is kept: {}", sidecar.display(), error);
+            }
+        }
+        // After the move, because a copy to another file system gets new times
+        if let Err(error) = copy_times_and_permissions(&source_metadata, &target_path) {
+            result += &format!(", times and permissions are not copied: {}", error);
+        }
+        Ok(result)
+    }
+
+    /// Returns extension of the output files of the input file
+    pub fn get_output_extension(&self, input_file: &InputFile) -> Option<String> {
+        match self.compress_settings.container.get_extension() {
+            Some(extension) => Some(extension.to_string()),
+            None => input_file
+                .path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_string()),
+        }
+    }
+
+    /// Returns problems of the output that don't prevent compressing
+    pub fn get_warnings(&self, input_file: &InputFile) -> Vec<String> {
+        let mut result: Vec<String> = input_file
+            .probe_errors
+            .iter()
+            .map(|error| error.to_string())
+            .collect();
+        if let Err(error) = self.get_segments(input_file) {
+            result.push(error);
+        }
+        let keeps_ass = self.compress_settings.subtitle_codec != SubtitleCodec::Srt
+            && input_file.sources.iter().enumerate().any(|(i, source)| {
+                matches!(source.stream_type, StreamType::Subtitle(_))
+                    && ["ass", "ssa"].contains(&source.codec_name.as_str())
+                    && self.get_source_state(input_file, i).0
+            });
+        let fonts: Vec<usize> = (0..input_file.sources.len())
+            .filter(|&i| match &input_file.sources[i].stream_type {
+                StreamType::Attachment(attachment_data) => attachment_data.is_font(),
+                _ => false,
+            })
+            .collect();
+        if keeps_ass && !fonts.is_empty() {
+            let extension = self
+                .get_output_extension(input_file)
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            let disabled_fonts = fonts
                 .iter()
-                .find(|ss| ss.stream == *source)
-                .unwrap();
-            let source_index = stream_setting.stream.stream_type.to_index();
-            if stream_setting.enabled {
+                .filter(|&&i| !self.get_source_state(input_file, i).0)
+                .count();
+            if !ATTACHMENT_CONTAINERS.contains(&extension.as_str()) {
+                result.push(format!(
+                    "ASS subtitles lose {} fonts in {}",
+                    fonts.len(),
+                    extension
+                ));
+            } else if disabled_fonts > 0 {
+                result.push(format!(
+                    "ASS subtitles lose {} disabled fonts",
+                    disabled_fonts
+                ));
+            }
+        }
+        result
+    }
+
+    /// Returns the cover image that replaces attached pictures of the file
+    pub fn get_cover_path(&self, input_file: &InputFile) -> Option<PathBuf> {
+        let cover = self.compress_settings.cover.as_ref()?;
+        let mut result = input_file
+            .path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        result.push(cover);
+        Some(result).filter(|path| path.is_file())
+    }
+
+    /// Returns indexes of the output sources with their indexes among output streams of
+    /// the same type. Output streams are grouped by type in the order of stream settings,
+    /// attached pictures go after the video streams.
+    fn get_output_sources(&self, input_file: &InputFile) -> Vec<(usize, usize)> {
+        let mut order: Vec<usize> = (0..input_file.sources.len()).collect();
+        order.sort_by_key(|&i| {
+            let source = &input_file.sources[i];
+            (
+                source.stream_type.to_index(),
+                matches!(source.stream_type, StreamType::Picture(_)),
+                self.get_settings_index(source),
+            )
+        });
+
+        let has_cover = self.get_cover_path(input_file).is_some();
+        let mut output_indexes = [0; 5];
+        order
+            .into_iter()
+            .filter(|&i| {
+                let is_picture =
+                    matches!(input_file.sources[i].stream_type, StreamType::Picture(_));
+                self.get_source_state(input_file, i).0 && !(has_cover && is_picture)
+            })
+            .map(|i| {
+                let output_index =
+                    &mut output_indexes[input_file.sources[i].stream_type.to_index()];
+                *output_index += 1;
+                (i, *output_index - 1)
+            })
+            .collect()
+    }
+
+    fn get_command_streams(&self, input_file: &InputFile) -> Vec<String> {
+        let mut result = Vec::new();
+        for (i, output_index) in self.get_output_sources(input_file) {
+            let source = &input_file.sources[i];
+            let (_, disposition) = self.get_source_state(input_file, i);
+            // Absolute index (input:index), streams that are left out by the probe don't
+            // shift it
+            result.extend(vec![
+                "-map".to_string(),
+                format!("{}:{}", input_file.get_input_index(source), source.index),
+            ]);
+            let stream_settings = self.get_settings(source);
+            let output_stream = format!("{}:{}", source.stream_type, output_index);
+            if let Some(title) = &stream_settings.title {
                 result.extend(vec![
-                    "-map".to_string(),
-                    format!("0:{}:{}", source.stream_type, source_indexes[source_index]),
+                    format!("-metadata:s:{}", output_stream),
+                    format!("title={}", title),
                 ]);
             }
-            if stream_setting.default {
+            if let Some(language) = &stream_settings.language {
                 result.extend(vec![
-                    format!("-disposition:{}", source.stream_type),
-                    (source_indexes[source_index] + 1).to_string(),
+                    format!("-metadata:s:{}", output_stream),
+                    format!("language={}", language),
                 ]);
             }
-            source_indexes[source_index] += 1;
-        });
+            // Full value is always set, so flags inherited from the source are cleared
+            if !matches!(
+                source.stream_type,
+                StreamType::Attachment(_) | StreamType::Data
+            ) {
+                result.extend(vec![
+                    format!("-disposition:{}", output_stream),
+                    disposition.to_string(),
+                ]);
+            }
+        }
+        result
+    }
+
+    /// Returns per-stream video arguments: encoded streams are filtered, attached pictures
+    /// and the cover image are copied
+    fn get_command_video_codecs(
+        &self,
+        input_file: &InputFile,
+        cover_index: Option<usize>,
+    ) -> Vec<String> {
+        let video_filter = self.compress_settings.get_video_filter();
+        let mut result = Vec::new();
+        for (i, output_index) in self.get_output_sources(input_file) {
+            match &input_file.sources[i].stream_type {
+                StreamType::Video(_) => {
+                    if let Some(video_filter) = &video_filter {
+                        result.extend(vec![
+                            format!("-filter:v:{}", output_index),
+                            video_filter.clone(),
+                        ]);
+                    }
+                }
+                StreamType::Picture(_) => {
+                    result.extend(vec![format!("-c:v:{}", output_index), "copy".to_string()]);
+                }
+                _ => {}
+            }
+        }
+        if let Some(cover_index) = cover_index {
+            result.extend(vec![format!("-c:v:{}", cover_index), "copy".to_string()]);
+        }
         result
     }
 
-    pub fn get_command(&self, input_file: &InputFile, output_path: &PathBuf) -> Vec<String> {
-        let mut result = vec![];
+    /// Returns segments of the file that are written to separate outputs.
+    /// Empty result means the whole file is written to one output.
+    pub fn get_segments(&self, input_file: &InputFile) -> Result<Vec<ChapterSegment>, String> {
+        let stem = input_file.path.file_stem().unwrap_or_default();
+        self.compress_settings.chapter_split.get_segments(
+            &input_file.chapters,
+            self.compress_settings.chapter_ranges.as_deref(),
+            &stem.to_string_lossy(),
+        )
+    }
+
+    /// Returns FFMETADATA with chapters of the output or None if the output has no chapters
+    pub fn get_chapters_metadata(
+        &self,
+        input_file: &InputFile,
+        segment: Option<&ChapterSegment>,
+    ) -> Option<String> {
+        if self.compress_settings.chapter_mapping == ChapterMapping::Drop {
+            return None;
+        }
+        let chapters: Vec<Chapter> = match segment {
+            Some(segment) => input_file
+                .chapters
+                .iter()
+                .filter_map(|chapter| chapter.in_segment(segment))
+                .collect(),
+            None => input_file.chapters.clone(),
+        };
+        if chapters.is_empty() {
+            return None;
+        }
+        Some(to_ffmetadata(&chapters))
+    }
+
+    pub fn get_command(
+        &self,
+        input_file: &InputFile,
+        output_path: &Path,
+        segment: Option<&ChapterSegment>,
+    ) -> Vec<String> {
+        let segment_arguments = match segment {
+            Some(segment) => vec![
+                "-ss".to_string(),
+                segment.start.to_string(),
+                "-to".to_string(),
+                segment.end.to_string(),
+            ],
+            None => Vec::new(),
+        };
+        // Existing outputs are handled by `get_output_path`
+        let mut result = vec!["-y".to_string()];
         result.extend(self.compress_settings.get_init_arguments());
+        result.extend(segment_arguments.clone());
         result.push("-i".to_string());
-        result.push(
-            input_file
-                .path
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-                .to_string(),
-        );
+        result.push(input_file.path.to_string_lossy().to_string());
+        for sidecar in &input_file.sidecars {
+            result.extend(segment_arguments.clone());
+            result.push("-i".to_string());
+            result.push(sidecar.to_string_lossy().to_string());
+        }
+        let has_chapters = self.get_chapters_metadata(input_file, segment).is_some();
+        if has_chapters {
+            result.extend(vec![
+                "-f".to_string(),
+                "ffmetadata".to_string(),
+                "-i".to_string(),
+                get_metadata_path(output_path).to_string_lossy().to_string(),
+            ]);
+        }
+        let cover_path = self.get_cover_path(input_file);
+        if let Some(cover_path) = &cover_path {
+            result.push("-i".to_string());
+            result.push(cover_path.to_string_lossy().to_string());
+        }
         result.extend(self.get_command_streams(input_file));
+        // Cover image goes after the video streams and attached pictures of the file
+        let cover_index = cover_path.map(|_| {
+            let output_index = self
+                .get_output_sources(input_file)
+                .iter()
+                .filter(|(i, _)| input_file.sources[*i].stream_type.to_index() == 0)
+                .count();
+            let input_index = input_file.sidecars.len() + 1 + usize::from(has_chapters);
+            result.extend(vec![
+                "-map".to_string(),
+                format!("{}:v:0", input_index),
+                format!("-disposition:v:{}", output_index),
+                "attached_pic".to_string(),
+            ]);
+            output_index
+        });
+        if has_chapters {
+            result.extend(vec![
+                "-map_chapters".to_string(),
+                (input_file.sidecars.len() + 1).to_string(),
+            ]);
+        } else {
+            result.extend(vec!["-map_chapters".to_string(), "-1".to_string()]);
+        }
         result.extend(self.compress_settings.get_compress_arguments());
+        result.extend(self.get_command_video_codecs(input_file, cover_index));
         result.push(output_path.to_string_lossy().to_string());
         result
     }
 }
 
-impl Default for FfmpegManager {
-    fn default() -> Self {
-        Self {
-            input_files: Vec::new(),
-            stream_settings: Vec::new(),
-            compress_settings: CompressSettings::default(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ffmpeg_manager::probe::parse_probe;
+    use serde_json::json;
+
+    fn get_input_file(sources: Vec<Stream>) -> InputFile {
+        InputFile {
+            path: PathBuf::from("/films/film.mkv"),
+            overrides: vec![StreamOverride::default(); sources.len()],
+            sources,
+            chapters: Vec::new(),
+            format: FormatData::default(),
+            sidecars: Vec::new(),
+            probe_errors: Vec::new(),
+            skip_override: None,
         }
     }
+
+    fn get_maps(command: &[String]) -> Vec<&str> {
+        command
+            .windows(2)
+            .filter(|arguments| arguments[0] == "-map")
+            .map(|arguments| arguments[1].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn maps_streams_by_probe_index() {
+        let probe_data = parse_probe(&json!({
+            "streams": [
+                {"index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080},
+                {"index": 1, "codec_type": "audio", "codec_name": "aac"},
+                {"index": 2, "codec_type": "audio", "codec_name": "opus", "channels": 2},
+                {"index": 3, "codec_type": "subtitle", "codec_name": "ass"},
+            ]
+        }))
+        .unwrap();
+        assert_eq!(probe_data.errors.len(), 1);
+        let mut ffmpeg_manager = FfmpegManager::default();
+        ffmpeg_manager
+            .input_files
+            .push(get_input_file(probe_data.streams));
+        ffmpeg_manager.update_stream_settings();
+
+        let command = ffmpeg_manager.get_command_streams(&ffmpeg_manager.input_files[0]);
+        assert_eq!(get_maps(&command), ["0:0", "0:2", "0:3"]);
+        // Output indexes are still counted per type
+        assert!(command.contains(&String::from("-disposition:a:0")));
+        assert!(command.contains(&String::from("-disposition:s:0")));
+    }
+
+    #[test]
+    fn streams_of_different_files_match_without_index() {
+        let stream = |index| Stream {
+            index,
+            stream_type: StreamType::Data,
+            codec_name: String::from("tmcd"),
+            disposition: Disposition::default(),
+            origin: None,
+        };
+        assert!(stream(1) == stream(4));
+    }
+
+    #[test]
+    fn command_template_matches_per_stream_filters() {
+        let probe_data = parse_probe(&json!({
+            "streams": [
+                {"index": 0, "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080},
+                {
+                    "index": 1, "codec_type": "video", "codec_name": "mjpeg", "width": 600, "height": 600,
+                    "disposition": {"attached_pic": 1},
+                },
+            ]
+        }))
+        .unwrap();
+        let mut ffmpeg_manager = FfmpegManager::default();
+        ffmpeg_manager.compress_settings.scale = Some(String::from("1280:-2"));
+        ffmpeg_manager
+            .input_files
+            .push(get_input_file(probe_data.streams));
+        ffmpeg_manager.update_stream_settings();
+        let video_filter = ffmpeg_manager.compress_settings.get_video_filter().unwrap();
+
+        let command = ffmpeg_manager.get_command(
+            &ffmpeg_manager.input_files[0],
+            Path::new("output/film.mkv"),
+            None,
+        );
+        let command = command.join(" ");
+        assert!(command.contains(&format!("-filter:v:0 {}", video_filter)));
+        assert!(command.contains("-c:v:1 copy"));
+        let template = ffmpeg_manager.get_command_template();
+        assert!(!template.contains("-vf"));
+        assert!(template.contains(&format!("-filter:v:<video> {}", video_filter)));
+        assert!(template.contains("-c:v:<cover> copy"));
+    }
 }
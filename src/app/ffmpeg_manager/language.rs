@@ -0,0 +1,77 @@
+/// ISO 639-2 codes with ISO 639-1 equivalents and English names
+pub const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("und", "", "Undetermined"),
+    ("ara", "ar", "Arabic"),
+    ("bel", "be", "Belarusian"),
+    ("bul", "bg", "Bulgarian"),
+    ("cat", "ca", "Catalan"),
+    ("ces", "cs", "Czech"),
+    ("dan", "da", "Danish"),
+    ("deu", "de", "German"),
+    ("ell", "el", "Greek"),
+    ("eng", "en", "English"),
+    ("est", "et", "Estonian"),
+    ("fas", "fa", "Persian"),
+    ("fin", "fi", "Finnish"),
+    ("fra", "fr", "French"),
+    ("heb", "he", "Hebrew"),
+    ("hin", "hi", "Hindi"),
+    ("hrv", "hr", "Croatian"),
+    ("hun", "hu", "Hungarian"),
+    ("ind", "id", "Indonesian"),
+    ("isl", "is", "Icelandic"),
+    ("ita", "it", "Italian"),
+    ("jpn", "ja", "Japanese"),
+    ("kat", "ka", "Georgian"),
+    ("kaz", "kk", "Kazakh"),
+    ("kor", "ko", "Korean"),
+    ("lav", "lv", "Latvian"),
+    ("lit", "lt", "Lithuanian"),
+    ("msa", "ms", "Malay"),
+    ("nld", "nl", "Dutch"),
+    ("nor", "no", "Norwegian"),
+    ("pol", "pl", "Polish"),
+    ("por", "pt", "Portuguese"),
+    ("ron", "ro", "Romanian"),
+    ("rus", "ru", "Russian"),
+    ("slk", "sk", "Slovak"),
+    ("slv", "sl", "Slovenian"),
+    ("spa", "es", "Spanish"),
+    ("srp", "sr", "Serbian"),
+    ("swe", "sv", "Swedish"),
+    ("tat", "tt", "Tatar"),
+    ("tha", "th", "Thai"),
+    ("tur", "tr", "Turkish"),
+    ("ukr", "uk", "Ukrainian"),
+    ("uzb", "uz", "Uzbek"),
+    ("vie", "vi", "Vietnamese"),
+    ("zho", "zh", "Chinese"),
+    ("mul", "", "Multiple languages"),
+];
+
+/// Alternative ISO 639-2/B codes that are used in some files
+const ALIASES: &[(&str, &str)] = &[
+    ("chi", "zho"),
+    ("cze", "ces"),
+    ("ger", "deu"),
+    ("gre", "ell"),
+    ("per", "fas"),
+    ("fre", "fra"),
+    ("dut", "nld"),
+    ("rum", "ron"),
+    ("slo", "slk"),
+];
+
+/// Returns ISO 639-2 code for ISO 639-1/639-2 code, or None for unknown code
+pub fn to_iso639_2(code: &str) -> Option<&'static str> {
+    let code = code.to_ascii_lowercase();
+    if let Some((_, iso639_2)) = ALIASES.iter().find(|(alias, _)| *alias == code) {
+        return Some(iso639_2);
+    }
+    LANGUAGES
+        .iter()
+        .find(|(iso639_2, iso639_1, _)| {
+            *iso639_2 == code || (!iso639_1.is_empty() && *iso639_1 == code)
+        })
+        .map(|(iso639_2, _, _)| *iso639_2)
+}
@@ -0,0 +1,232 @@
+use crate::app::ffmpeg_manager::chapter::get_metadata_path;
+use crate::app::ffmpeg_manager::output::get_partial_path;
+use crate::app::ffmpeg_manager::xdg::get_cache_dir;
+use serde_json::{Value, json};
+use std::fmt::{Display, Formatter};
+use std::fs::{create_dir_all, metadata, read_to_string, remove_file, write};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+#[derive(Debug, EnumIter, PartialEq, Eq, Clone, Copy)]
+pub enum OutputState {
+    /// Encoding started, but didn't finish
+    Pending,
+    Done,
+    Failed,
+    /// The output existed and was not written
+    Skipped,
+    /// The output replaced its input
+    Replaced,
+}
+
+impl Display for OutputState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputState::Pending => f.write_str("pending"),
+            OutputState::Done => f.write_str("done"),
+            OutputState::Failed => f.write_str("failed"),
+            OutputState::Skipped => f.write_str("skipped"),
+            OutputState::Replaced => f.write_str("replaced"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ManifestEntry {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub state: OutputState,
+    /// Size of the finished output, a changed size means the output was modified
+    pub size: Option<u64>,
+    /// Reason of the failure
+    pub error: Option<String>,
+    /// What happened to the original in the replace mode
+    pub note: Option<String>,
+}
+
+/// Outputs of the last batch and their states. It is saved after every output, so an
+/// interrupted batch can be resumed.
+#[derive(Debug, Default, Clone)]
+pub struct Manifest {
+    /// Output folder of the batch, None if outputs are written next to the sources
+    pub output_root: Option<PathBuf>,
+    pub entries: Vec<ManifestEntry>,
+}
+
+fn get_manifest_path() -> PathBuf {
+    let mut result = get_cache_dir();
+    result.push("batch.json");
+    result
+}
+
+impl Manifest {
+    pub fn new(output_root: Option<PathBuf>) -> Self {
+        Self {
+            output_root,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Loads the manifest of the last batch, None if there is no unfinished batch
+    pub fn load() -> Option<Self> {
+        let manifest: Value =
+            serde_json::from_str(&read_to_string(get_manifest_path()).ok()?).ok()?;
+        let entries = manifest["outputs"]
+            .as_array()?
+            .iter()
+            .filter_map(|entry| {
+                Some(ManifestEntry {
+                    input: PathBuf::from(entry["input"].as_str()?),
+                    output: PathBuf::from(entry["output"].as_str()?),
+                    state: OutputState::iter()
+                        .find(|state| entry["state"].as_str() == Some(&state.to_string()))?,
+                    size: entry["size"].as_u64(),
+                    error: entry["error"].as_str().map(|error| error.to_string()),
+                    note: entry["note"].as_str().map(|note| note.to_string()),
+                })
+            })
+            .collect();
+        Some(Self {
+            output_root: manifest["output_root"].as_str().map(PathBuf::from),
+            entries,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let path = get_manifest_path();
+        create_dir_all(path.parent().unwrap())?;
+        let outputs: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "input": entry.input.to_string_lossy(),
+                    "output": entry.output.to_string_lossy(),
+                    "state": entry.state.to_string(),
+                    "size": entry.size,
+                    "error": entry.error,
+                    "note": entry.note,
+                })
+            })
+            .collect();
+        let manifest = json!({
+            "output_root": self.output_root.as_ref().map(|root| root.to_string_lossy()),
+            "outputs": outputs,
+        });
+        write(path, serde_json::to_string_pretty(&manifest)?)
+    }
+
+    /// Removes the manifest of the finished batch
+    pub fn remove() -> Result<(), Error> {
+        match remove_file(get_manifest_path()) {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn contains_input(&self, input: &Path) -> bool {
+        self.entries.iter().any(|entry| entry.input == input)
+    }
+
+    pub fn get_entry(&self, output: &Path) -> Option<&ManifestEntry> {
+        self.entries.iter().find(|entry| entry.output == output)
+    }
+
+    /// Sets the state of the output, the size is taken from the file when it is done
+    pub fn set_state(&mut self, input: &Path, output: &Path, state: OutputState) {
+        let size = match state {
+            OutputState::Done => metadata(output).ok().map(|metadata| metadata.len()),
+            _ => None,
+        };
+        self.set_entry(input, output, state, size, None);
+    }
+
+    pub fn set_failed(&mut self, input: &Path, output: &Path, error: String) {
+        self.set_entry(input, output, OutputState::Failed, None, Some(error));
+    }
+
+    fn set_entry(
+        &mut self,
+        input: &Path,
+        output: &Path,
+        state: OutputState,
+        size: Option<u64>,
+        error: Option<String>,
+    ) {
+        match self.entries.iter_mut().find(|entry| entry.output == output) {
+            Some(entry) => {
+                entry.state = state;
+                entry.size = size;
+                entry.error = error;
+                entry.note = None;
+            }
+            None => self.entries.push(ManifestEntry {
+                input: input.to_path_buf(),
+                output: output.to_path_buf(),
+                state,
+                size,
+                error,
+                note: None,
+            }),
+        }
+    }
+
+    pub fn set_note(&mut self, output: &Path, note: String) {
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.output == output) {
+            entry.note = Some(note);
+        }
+    }
+
+    /// Removes partial files of the outputs that were not finished, with their chapters
+    pub fn remove_partials(&self) {
+        for entry in &self.entries {
+            if matches!(entry.state, OutputState::Pending | OutputState::Failed) {
+                let partial_path = get_partial_path(&entry.output);
+                let _ = remove_file(get_metadata_path(&partial_path));
+                let _ = remove_file(partial_path);
+            }
+        }
+    }
+
+    /// Returns true if every output is done, skipped or replaced its input
+    pub fn is_finished(&self) -> bool {
+        self.entries.iter().all(|entry| {
+            matches!(
+                entry.state,
+                OutputState::Done | OutputState::Skipped | OutputState::Replaced
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::remove_dir_all;
+
+    #[test]
+    fn removes_partials_of_unfinished_outputs() {
+        let folder = std::env::temp_dir().join("film_compressor_manifest_test");
+        let _ = remove_dir_all(&folder);
+        create_dir_all(&folder).unwrap();
+        let mut manifest = Manifest::new(Some(folder.clone()));
+        let pending = folder.join("pending.mkv");
+        let done = folder.join("done.mkv");
+        manifest.set_state(Path::new("pending.mkv"), &pending, OutputState::Pending);
+        manifest.set_state(Path::new("done.mkv"), &done, OutputState::Done);
+        let files = [
+            get_partial_path(&pending),
+            get_metadata_path(&get_partial_path(&pending)),
+            get_partial_path(&done),
+        ];
+        for file in &files {
+            write(file, "").unwrap();
+        }
+        manifest.remove_partials();
+        let exists = files.each_ref().map(|file| file.exists());
+        let _ = remove_dir_all(&folder);
+        assert_eq!(exists, [false, false, true]);
+    }
+}
@@ -1,6 +1,39 @@
+mod attributes;
+mod chapter;
 mod codec;
 mod compress_settings;
+mod disposition;
+#[allow(clippy::module_inception)]
 mod ffmpeg_manager;
+mod language;
+mod manifest;
+mod output;
+mod preset;
+mod probe;
+mod probe_cache;
+mod probe_pool;
+mod replace;
+mod scan;
+mod sidecar;
+mod skip_rules;
+mod stream_rules;
+mod verify;
+mod xdg;
 
-pub use codec::{AudioCodec, PixelFormat, SubtitleCodec, VideoCodec};
+pub use attributes::copy_attributes;
+pub use chapter::{
+    Chapter, ChapterMapping, ChapterSplit, get_metadata_path, import_chapters, normalize_chapters,
+    parse_timestamp,
+};
+pub use codec::{AudioCodec, Container, PixelFormat, SubtitleCodec, VideoCodec};
+pub use disposition::DISPOSITION_FLAGS;
 pub use ffmpeg_manager::FfmpegManager;
+pub use language::LANGUAGES;
+pub use manifest::{Manifest, ManifestEntry, OutputState};
+pub use output::{ExistingOutput, OutputLocation, get_partial_path};
+pub use preset::{load_preset, save_preset};
+pub use probe_cache::clear_probe_cache;
+pub use replace::ReplaceOriginal;
+pub use scan::ScanSettings;
+pub use skip_rules::SkipRules;
+pub use stream_rules::StreamRules;
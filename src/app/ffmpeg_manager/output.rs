@@ -0,0 +1,213 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use strum_macros::EnumIter;
+
+/// File name template of the outputs, the placeholders are {stem}, {codec}, {height},
+/// {ext} and {date}
+pub const DEFAULT_TEMPLATE: &str = "{stem}.{ext}";
+
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
+pub enum OutputLocation {
+    /// Output folder with the structure of the scanned folders
+    #[default]
+    OutputFolder,
+    /// The folder of the input file
+    NextToSource,
+}
+
+impl Display for OutputLocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputLocation::OutputFolder => f.write_str("output folder"),
+            OutputLocation::NextToSource => f.write_str("next to source"),
+        }
+    }
+}
+
+/// What to do with an output that already exists
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
+pub enum ExistingOutput {
+    Skip,
+    Overwrite,
+    /// Writes to "name (n).ext"
+    Rename,
+    /// Skips the output if it is complete, otherwise overwrites it
+    #[default]
+    SkipIfValid,
+}
+
+impl Display for ExistingOutput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExistingOutput::Skip => f.write_str("skip"),
+            ExistingOutput::Overwrite => f.write_str("overwrite"),
+            ExistingOutput::Rename => f.write_str("rename"),
+            ExistingOutput::SkipIfValid => f.write_str("skip if valid"),
+        }
+    }
+}
+
+/// Returns the first of "name.ext", "name (1).ext", ... that doesn't exist
+pub fn get_free_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut result = path.to_path_buf();
+    let mut index = 0usize;
+    while result.exists() {
+        index += 1;
+        // Built as a whole name, set_extension would cut "film.mkv (1)" of "film.mkv.bak"
+        let file_name = match path.extension() {
+            Some(extension) => format!("{} ({}).{}", stem, index, extension.to_string_lossy()),
+            None => format!("{} ({})", stem, index),
+        };
+        result.set_file_name(file_name);
+    }
+    result
+}
+
+/// Returns the name of the automatic output folder: "output", "output (1)", ...
+pub fn get_output_folder_name(index: usize) -> String {
+    if index == 0 {
+        String::from("output")
+    } else {
+        format!("output ({})", index)
+    }
+}
+
+/// Returns true for names of the automatic output folders
+pub fn is_output_folder_name(name: &str) -> bool {
+    name == "output"
+        || name
+            .strip_prefix("output (")
+            .and_then(|index| index.strip_suffix(')'))
+            .is_some_and(|index| index.parse::<usize>().is_ok())
+}
+
+/// Returns true for the files of unfinished outputs: "name.partial.ext"
+pub fn is_partial_path(path: &Path) -> bool {
+    path.file_stem()
+        .is_some_and(|stem| stem.to_string_lossy().ends_with(".partial"))
+        || path
+            .extension()
+            .is_some_and(|extension| extension == "partial")
+}
+
+/// Returns the path the output is written to until it is complete: "name.partial.ext".
+/// The extension is kept, because ffmpeg chooses the muxer by it.
+pub fn get_partial_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(".partial");
+    if let Some(extension) = path.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    path.with_file_name(file_name)
+}
+
+/// Replaces "{name}" placeholders with values, unknown placeholders are kept
+pub fn fill_template(template: &str, values: &[(&str, String)]) -> String {
+    let mut result = template.to_string();
+    for (name, value) in values {
+        result = result.replace(&format!("{{{}}}", name), value);
+    }
+    result
+}
+
+/// Returns the current UTC date: "2024-05-31"
+pub fn get_date() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / 86400)
+        .unwrap_or_default() as i64;
+    // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    #[test]
+    fn finds_free_paths() {
+        let folder = std::env::temp_dir().join("film_compressor_output_test");
+        let _ = remove_dir_all(&folder);
+        create_dir_all(&folder).unwrap();
+        for file in [
+            "film.mkv",
+            "film (1).mkv",
+            "film.mkv.bak",
+            "film.bak",
+            "notes",
+        ] {
+            write(folder.join(file), "").unwrap();
+        }
+        let paths = [
+            get_free_path(&folder.join("film.mkv")),
+            get_free_path(&folder.join("film.mkv.bak")),
+            get_free_path(&folder.join("notes")),
+            get_free_path(&folder.join("other.mkv")),
+        ];
+        let _ = remove_dir_all(&folder);
+        assert_eq!(
+            paths,
+            [
+                folder.join("film (2).mkv"),
+                folder.join("film.mkv (1).bak"),
+                folder.join("notes (1)"),
+                folder.join("other.mkv"),
+            ]
+        );
+    }
+
+    #[test]
+    fn fills_template() {
+        let values = [
+            ("stem", String::from("Movie")),
+            ("height", String::from("720")),
+            ("ext", String::from("mkv")),
+        ];
+        assert_eq!(fill_template(DEFAULT_TEMPLATE, &values), "Movie.mkv");
+        assert_eq!(
+            fill_template("{stem} [{height}p] {unknown}.{ext}", &values),
+            "Movie [720p] {unknown}.mkv"
+        );
+    }
+
+    #[test]
+    fn names_output_folders() {
+        assert_eq!(get_output_folder_name(0), "output");
+        assert_eq!(get_output_folder_name(3), "output (3)");
+        assert!(is_output_folder_name(&get_output_folder_name(0)));
+        assert!(is_output_folder_name(&get_output_folder_name(12)));
+        assert!(!is_output_folder_name("output (x)"));
+        assert!(!is_output_folder_name("outputs"));
+        assert_eq!(get_date().len(), 10);
+    }
+
+    #[test]
+    fn names_partial_files() {
+        let partial_path = get_partial_path(Path::new("output/Movie.2024.mkv"));
+        assert_eq!(partial_path, Path::new("output/Movie.2024.partial.mkv"));
+        assert!(is_partial_path(&partial_path));
+        assert!(is_partial_path(&get_partial_path(Path::new(
+            "output/Movie"
+        ))));
+        assert!(!is_partial_path(Path::new("output/Movie.mkv")));
+        assert!(!is_partial_path(Path::new("output/Impartial.mkv")));
+    }
+}
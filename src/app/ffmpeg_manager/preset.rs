@@ -0,0 +1,114 @@
+use crate::app::ffmpeg_manager::compress_settings::CompressSettings;
+use crate::app::ffmpeg_manager::skip_rules::SkipRules;
+use crate::app::ffmpeg_manager::stream_rules::StreamRules;
+use crate::app::ffmpeg_manager::xdg::get_config_dir;
+use serde_json::{Value, json};
+use std::fmt::Display;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use strum::IntoEnumIterator;
+
+/// Returns the preset file, names that lead out of the presets folder are rejected
+fn get_preset_path(name: &str) -> Result<PathBuf, Error> {
+    if name.contains(['/', '\\']) || name.contains("..") || Path::new(name).is_absolute() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid preset name \"{}\"", name),
+        ));
+    }
+    let mut result = get_config_dir();
+    result.push("presets");
+    result.push(format!("{}.json", name));
+    Ok(result)
+}
+
+fn parse_enum<T: IntoEnumIterator + Display>(value: &Value) -> Option<T> {
+    let value = value.as_str()?;
+    T::iter().find(|variant| variant.to_string() == value)
+}
+
+fn parse_string(value: &Value) -> Option<String> {
+    value.as_str().map(|s| s.to_string())
+}
+
+pub fn save_preset(name: &str, compress_settings: &CompressSettings) -> Result<(), Error> {
+    let path = get_preset_path(name)?;
+    create_dir_all(path.parent().unwrap())?;
+    let preset = json!({
+        "video_codec": compress_settings.video_codec.to_string(),
+        "pixel_format": compress_settings.pixel_format.to_string(),
+        "audio_codec": compress_settings.audio_codec.to_string(),
+        "subtitle_codec": compress_settings.subtitle_codec.to_string(),
+        "video_bitrate": compress_settings.video_bitrate,
+        "audio_bitrate": compress_settings.audio_bitrate,
+        "crop": compress_settings.crop,
+        "scale": compress_settings.scale,
+        "other_settings": compress_settings.other_settings,
+        "chapter_split": compress_settings.chapter_split.to_string(),
+        "chapter_ranges": compress_settings.chapter_ranges,
+        "chapter_mapping": compress_settings.chapter_mapping.to_string(),
+        "stream_rules": compress_settings.stream_rules.to_string(),
+        "cover": compress_settings.cover,
+        "container": compress_settings.container.to_string(),
+        "output_folder": compress_settings.output_folder,
+        "output_location": compress_settings.output_location.to_string(),
+        "output_template": compress_settings.output_template,
+        "existing_output": compress_settings.existing_output.to_string(),
+        "replace_original": compress_settings.replace_original.to_string(),
+        "min_saving": compress_settings.min_saving,
+        "trash_folder": compress_settings.trash_folder,
+        "backup_suffix": compress_settings.backup_suffix,
+        "copy_attributes": compress_settings.copy_attributes,
+        "skip_rules": compress_settings.skip_rules.to_string(),
+    });
+    write(path, serde_json::to_string_pretty(&preset)?)
+}
+
+/// Loads preset. Missing or unknown values are set to default.
+pub fn load_preset(name: &str) -> Result<CompressSettings, Error> {
+    let preset: Value = serde_json::from_str(&read_to_string(get_preset_path(name)?)?)?;
+    Ok(CompressSettings {
+        video_codec: parse_enum(&preset["video_codec"]).unwrap_or_default(),
+        pixel_format: parse_enum(&preset["pixel_format"]).unwrap_or_default(),
+        audio_codec: parse_enum(&preset["audio_codec"]).unwrap_or_default(),
+        subtitle_codec: parse_enum(&preset["subtitle_codec"]).unwrap_or_default(),
+        video_bitrate: parse_string(&preset["video_bitrate"]),
+        audio_bitrate: parse_string(&preset["audio_bitrate"]),
+        crop: parse_string(&preset["crop"]),
+        scale: parse_string(&preset["scale"]),
+        other_settings: parse_string(&preset["other_settings"]).unwrap_or_default(),
+        chapter_split: parse_enum(&preset["chapter_split"]).unwrap_or_default(),
+        chapter_ranges: parse_string(&preset["chapter_ranges"]),
+        chapter_mapping: parse_enum(&preset["chapter_mapping"]).unwrap_or_default(),
+        stream_rules: StreamRules::parse(preset["stream_rules"].as_str().unwrap_or_default())
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?,
+        cover: parse_string(&preset["cover"]),
+        container: parse_enum(&preset["container"]).unwrap_or_default(),
+        output_folder: parse_string(&preset["output_folder"]),
+        output_location: parse_enum(&preset["output_location"]).unwrap_or_default(),
+        output_template: parse_string(&preset["output_template"]),
+        existing_output: parse_enum(&preset["existing_output"]).unwrap_or_default(),
+        replace_original: parse_enum(&preset["replace_original"]).unwrap_or_default(),
+        min_saving: parse_string(&preset["min_saving"]),
+        trash_folder: parse_string(&preset["trash_folder"]),
+        backup_suffix: parse_string(&preset["backup_suffix"]),
+        copy_attributes: preset["copy_attributes"].as_bool().unwrap_or_default(),
+        skip_rules: SkipRules::parse(preset["skip_rules"].as_str().unwrap_or_default()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_preset_names_outside_of_presets_folder() {
+        assert!(get_preset_path("anime 1080p").is_ok());
+        assert!(get_preset_path("../config").is_err());
+        assert!(get_preset_path("..").is_err());
+        assert!(get_preset_path("a/b").is_err());
+        assert!(get_preset_path("a\\b").is_err());
+        assert!(get_preset_path("/tmp/preset").is_err());
+    }
+}
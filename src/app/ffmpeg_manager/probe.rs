@@ -0,0 +1,346 @@
+use crate::app::ffmpeg_manager::chapter::{Chapter, format_timestamp};
+use crate::app::ffmpeg_manager::disposition::Disposition;
+use crate::app::ffmpeg_manager::ffmpeg_manager::{
+    AttachmentData, AudioData, Stream, StreamType, SubtitleData, VideoData,
+};
+use crate::app::ffmpeg_manager::probe_cache::{load_probe, save_probe};
+use serde_json::Value;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum ProbeError {
+    /// ffprobe is not installed or not in PATH
+    FfprobeMissing,
+    Io(io::Error),
+    /// ffprobe exited with non-zero status, contains its error output
+    Failed(String),
+    InvalidJson(String),
+    /// The file was probed, but it has no streams
+    NoStreams,
+    /// The stream is skipped because of the missing field
+    MissingField {
+        stream: u64,
+        field: &'static str,
+    },
+    /// Probing of the sidecar file failed, so its streams are not added
+    Sidecar {
+        suffix: String,
+        error: Box<ProbeError>,
+    },
+    /// The scanned file is a short sample clip, contains its duration in seconds
+    Sample(f64),
+}
+
+impl Display for ProbeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProbeError::FfprobeMissing => f.write_str("ffprobe is not found"),
+            ProbeError::Io(error) => write!(f, "{}", error),
+            ProbeError::Failed(message) if message.is_empty() => {
+                f.write_str("ffprobe exited with non-zero status code")
+            }
+            ProbeError::Failed(message) => write!(f, "ffprobe failed: {}", message),
+            ProbeError::InvalidJson(message) => write!(f, "invalid ffprobe output: {}", message),
+            ProbeError::NoStreams => f.write_str("no streams"),
+            ProbeError::MissingField { stream, field } => {
+                write!(f, "stream {} is skipped: no {}", stream, field)
+            }
+            ProbeError::Sidecar { suffix, error } => write!(f, "sidecar {}: {}", suffix, error),
+            ProbeError::Sample(duration) => write!(f, "sample clip of {:.0} seconds", duration),
+        }
+    }
+}
+
+impl std::error::Error for ProbeError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RejectReason {
+    NotMedia,
+    ProbeFailed,
+    PermissionDenied,
+    Sample,
+}
+
+impl Display for RejectReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RejectReason::NotMedia => f.write_str("not media"),
+            RejectReason::ProbeFailed => f.write_str("probe failed"),
+            RejectReason::PermissionDenied => f.write_str("permission denied"),
+            RejectReason::Sample => f.write_str("sample"),
+        }
+    }
+}
+
+impl ProbeError {
+    pub fn get_reject_reason(&self) -> RejectReason {
+        match self {
+            ProbeError::Io(error) if error.kind() == ErrorKind::PermissionDenied => {
+                RejectReason::PermissionDenied
+            }
+            ProbeError::Failed(message) if message.ends_with("Permission denied") => {
+                RejectReason::PermissionDenied
+            }
+            ProbeError::Failed(message)
+                if message.ends_with("Invalid data found when processing input") =>
+            {
+                RejectReason::NotMedia
+            }
+            ProbeError::NoStreams => RejectReason::NotMedia,
+            ProbeError::Sample(_) => RejectReason::Sample,
+            _ => RejectReason::ProbeFailed,
+        }
+    }
+}
+
+/// Container-level data from `-show_format`
+#[derive(Debug, Default, Clone)]
+pub struct FormatData {
+    pub format_name: String,
+    /// Duration in seconds
+    pub duration: Option<f64>,
+    /// Size in bytes
+    pub size: Option<u64>,
+    /// Overall bitrate in bits per second
+    pub bit_rate: Option<u64>,
+    pub tags: Vec<(String, String)>,
+}
+
+impl FormatData {
+    fn from_json(format: &Value) -> Self {
+        Self {
+            format_name: format["format_name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            duration: parse_number(&format["duration"]),
+            size: parse_number(&format["size"]),
+            bit_rate: parse_number(&format["bit_rate"]),
+            tags: format["tags"]
+                .as_object()
+                .map(|tags| {
+                    tags.iter()
+                        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns lines for the file details pane
+    pub fn get_details(&self) -> Vec<String> {
+        let mut result = vec![format!("Format: {}", self.format_name)];
+        if let Some(duration) = self.duration {
+            result.push(format!("Duration: {}", format_timestamp(duration)));
+        }
+        if let Some(size) = self.size {
+            result.push(format!("Size: {}", format_size(size)));
+        }
+        if let Some(bit_rate) = self.bit_rate {
+            result.push(format!("Bitrate: {} kb/s", bit_rate / 1000));
+        }
+        result.extend(
+            self.tags
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, value)),
+        );
+        result
+    }
+}
+
+/// Formats size in bytes: "1.46 GiB"
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size, UNITS[unit])
+    } else {
+        format!("{:.2} {}", size, UNITS[unit])
+    }
+}
+
+/// ffprobe writes most numbers as strings
+fn parse_number<T: FromStr>(value: &Value) -> Option<T> {
+    match value {
+        Value::String(value) => value.parse().ok(),
+        Value::Number(value) => value.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Result of a successful probe. Streams that can't be parsed are left out and their
+/// reasons are kept in `errors`.
+#[derive(Default)]
+pub struct ProbeData {
+    pub streams: Vec<Stream>,
+    pub chapters: Vec<Chapter>,
+    pub format: FormatData,
+    pub errors: Vec<ProbeError>,
+}
+
+/// Runs ffprobe on the file and returns its JSON output
+fn run_ffprobe(path: &Path) -> Result<Value, ProbeError> {
+    let output = Command::new("ffprobe")
+        .arg("-v")
+        .arg("error")
+        .arg("-show_format")
+        .arg("-show_streams")
+        .arg("-show_chapters")
+        .arg("-output_format")
+        .arg("json")
+        .arg(path)
+        .output()
+        .map_err(|error| match error.kind() {
+            ErrorKind::NotFound => ProbeError::FfprobeMissing,
+            _ => ProbeError::Io(error),
+        })?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(ProbeError::Failed(
+            message
+                .lines()
+                .last()
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .map_err(|error| ProbeError::InvalidJson(error.to_string()))
+}
+
+/// Probes the file, ffprobe output is taken from the probe cache when the file is unchanged
+pub fn probe(path: &Path) -> Result<ProbeData, ProbeError> {
+    // Checks that the file exists and is readable before ffprobe hides the reason
+    File::open(path).map_err(ProbeError::Io)?;
+    let json_data = match load_probe(path) {
+        Some(json_data) => json_data,
+        None => {
+            let json_data = run_ffprobe(path)?;
+            save_probe(path, &json_data);
+            json_data
+        }
+    };
+    parse_probe(&json_data)
+}
+
+/// Probes the file without the cache, for temporary files that are renamed right after
+pub fn probe_uncached(path: &Path) -> Result<ProbeData, ProbeError> {
+    parse_probe(&run_ffprobe(path)?)
+}
+
+/// Parses ffprobe output. Streams with missing fields are left out, but keep their
+/// indexes, so the indexes of the other streams are not shifted.
+pub fn parse_probe(json_data: &Value) -> Result<ProbeData, ProbeError> {
+    let Some(streams) = json_data["streams"].as_array() else {
+        return Err(ProbeError::InvalidJson(String::from("no streams array")));
+    };
+
+    let mut result = ProbeData::default();
+    for (i, stream) in streams.iter().enumerate() {
+        let index = stream["index"].as_u64().unwrap_or(i as u64);
+        match parse_stream(stream, index) {
+            Ok(Some(stream)) => result.streams.push(stream),
+            Ok(None) => {}
+            Err(field) => result.errors.push(ProbeError::MissingField {
+                stream: index,
+                field,
+            }),
+        }
+    }
+    if result.streams.is_empty() && result.errors.is_empty() {
+        return Err(ProbeError::NoStreams);
+    }
+    result.chapters = json_data["chapters"]
+        .as_array()
+        .map(|chapters| chapters.iter().filter_map(Chapter::from_json).collect())
+        .unwrap_or_default();
+    result.format = FormatData::from_json(&json_data["format"]);
+    Ok(result)
+}
+
+fn get_u64(stream: &Value, field: &'static str) -> Result<u64, &'static str> {
+    stream[field].as_u64().ok_or(field)
+}
+
+fn get_string(stream: &Value, field: &str) -> Option<String> {
+    stream[field].as_str().map(|s| s.to_string())
+}
+
+/// Parses ffprobe rational like "24000/1001", "0/0" is unknown
+fn parse_rational(value: &Value) -> Option<f64> {
+    let (numerator, denominator) = value.as_str()?.split_once('/')?;
+    let numerator: f64 = numerator.parse().ok()?;
+    let denominator: f64 = denominator.parse().ok()?;
+    Some(numerator / denominator).filter(|rational| rational.is_finite() && *rational > 0.0)
+}
+
+fn get_tag(stream: &Value, tag: &str) -> Option<String> {
+    stream["tags"][tag].as_str().map(|s| s.to_string())
+}
+
+/// Returns None for streams of unsupported type or the name of the missing field
+fn parse_stream(stream: &Value, index: u64) -> Result<Option<Stream>, &'static str> {
+    let disposition = Disposition::from_json(&stream["disposition"]);
+    let stream_type = match stream["codec_type"].as_str().ok_or("codec_type")? {
+        "video" => {
+            let video_data = VideoData {
+                resolution: (get_u64(stream, "width")?, get_u64(stream, "height")?),
+                avg_frame_rate: parse_rational(&stream["avg_frame_rate"]),
+                r_frame_rate: parse_rational(&stream["r_frame_rate"]),
+                bit_rate: parse_number(&stream["bit_rate"]),
+                bits_per_raw_sample: parse_number(&stream["bits_per_raw_sample"]),
+                sample_aspect_ratio: get_string(stream, "sample_aspect_ratio"),
+                display_aspect_ratio: get_string(stream, "display_aspect_ratio"),
+                field_order: get_string(stream, "field_order"),
+            };
+            if disposition.attached_pic {
+                StreamType::Picture(video_data)
+            } else {
+                StreamType::Video(video_data)
+            }
+        }
+        "audio" => StreamType::Audio(AudioData {
+            title: get_tag(stream, "title"),
+            channels: get_u64(stream, "channels")?,
+            language: get_tag(stream, "language"),
+            channel_layout: get_string(stream, "channel_layout"),
+            sample_rate: parse_number(&stream["sample_rate"]),
+            bit_rate: parse_number(&stream["bit_rate"]),
+            bits_per_raw_sample: parse_number(&stream["bits_per_raw_sample"]),
+        }),
+        "subtitle" => StreamType::Subtitle(SubtitleData {
+            title: get_tag(stream, "title"),
+            language: get_tag(stream, "language"),
+        }),
+        "attachment" => StreamType::Attachment(AttachmentData {
+            filename: get_tag(stream, "filename"),
+            mimetype: get_tag(stream, "mimetype"),
+        }),
+        "data" => StreamType::Data,
+        _ => return Ok(None),
+    };
+    Ok(Some(Stream {
+        index,
+        stream_type,
+        // Data streams like timecode have no codec name
+        codec_name: stream["codec_name"]
+            .as_str()
+            .or(stream["codec_tag_string"].as_str())
+            .ok_or("codec_name")?
+            .to_string(),
+        disposition,
+        origin: None,
+    }))
+}
@@ -0,0 +1,71 @@
+use crate::app::ffmpeg_manager::xdg::get_cache_dir;
+use serde_json::{Value, json};
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{create_dir_all, metadata, read_to_string, remove_dir_all, write};
+use std::hash::{Hash, Hasher};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+fn get_probe_cache_dir() -> PathBuf {
+    let mut result = get_cache_dir();
+    result.push("probe");
+    result
+}
+
+fn get_entry_path(path: &Path) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    let mut result = get_probe_cache_dir();
+    result.push(format!("{:016x}.json", hasher.finish()));
+    result
+}
+
+/// Returns the cache key of the file: canonical path, size and mtime.
+/// The entry is invalid if any of them changes.
+fn get_key(path: &Path) -> Option<(PathBuf, Value)> {
+    let path = path.canonicalize().ok()?;
+    let metadata = metadata(&path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    let key = json!({
+        "path": path.to_string_lossy(),
+        "size": metadata.len(),
+        "modified_secs": modified.as_secs(),
+        "modified_nanos": modified.subsec_nanos(),
+    });
+    Some((path, key))
+}
+
+/// Returns cached ffprobe output of the file if the file has not changed
+pub fn load_probe(path: &Path) -> Option<Value> {
+    let (path, key) = get_key(path)?;
+    let mut entry: Value =
+        serde_json::from_str(&read_to_string(get_entry_path(&path)).ok()?).ok()?;
+    if entry["key"] != key {
+        return None;
+    }
+    Some(entry["output"].take())
+}
+
+/// Stores ffprobe output of the file. The cache is optional, so errors are ignored.
+pub fn save_probe(path: &Path, output: &Value) {
+    let Some((path, key)) = get_key(path) else {
+        return;
+    };
+    let entry_path = get_entry_path(&path);
+    if create_dir_all(entry_path.parent().unwrap()).is_err() {
+        return;
+    }
+    let entry = json!({
+        "key": key,
+        "output": output,
+    });
+    let _ = write(entry_path, entry.to_string());
+}
+
+pub fn clear_probe_cache() -> Result<(), Error> {
+    match remove_dir_all(get_probe_cache_dir()) {
+        Err(error) if error.kind() != ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
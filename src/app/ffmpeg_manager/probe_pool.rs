@@ -0,0 +1,85 @@
+use crate::app::ffmpeg_manager::ffmpeg_manager::InputFile;
+use crate::app::ffmpeg_manager::probe::ProbeError;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub type ProbeResult = (PathBuf, Result<InputFile, ProbeError>);
+
+/// Probes files on background threads, so the UI is drawn while a big folder is probed
+pub struct ProbePool {
+    /// Queued paths and the number of running workers. Workers exit when the queue is
+    /// empty, both are changed under one lock so no path is left without a worker.
+    queue: Arc<Mutex<(VecDeque<PathBuf>, usize)>>,
+    sender: Sender<ProbeResult>,
+    receiver: Receiver<ProbeResult>,
+    /// Files queued since the pool was idle
+    pub queued: usize,
+    /// Files probed since the pool was idle
+    pub done: usize,
+}
+
+impl Default for ProbePool {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            queue: Arc::default(),
+            sender,
+            receiver,
+            queued: 0,
+            done: 0,
+        }
+    }
+}
+
+impl ProbePool {
+    pub fn push(&mut self, path: PathBuf) {
+        self.queued += 1;
+        let mut queue = self.queue.lock().unwrap();
+        queue.0.push_back(path);
+        let max_workers = thread::available_parallelism()
+            .map(|workers| workers.get())
+            .unwrap_or(4);
+        if queue.1 < max_workers {
+            queue.1 += 1;
+            let queue = self.queue.clone();
+            let sender = self.sender.clone();
+            thread::spawn(move || run_worker(queue, sender));
+        }
+    }
+
+    /// Returns probed files without waiting for the rest
+    pub fn receive(&mut self) -> Vec<ProbeResult> {
+        let result: Vec<ProbeResult> = self.receiver.try_iter().collect();
+        self.done += result.len();
+        if self.done == self.queued {
+            self.done = 0;
+            self.queued = 0;
+        }
+        result
+    }
+
+    pub fn is_probing(&self) -> bool {
+        self.queued > 0
+    }
+}
+
+fn run_worker(queue: Arc<Mutex<(VecDeque<PathBuf>, usize)>>, sender: Sender<ProbeResult>) {
+    loop {
+        let path = {
+            let mut queue = queue.lock().unwrap();
+            match queue.0.pop_front() {
+                Some(path) => path,
+                None => {
+                    queue.1 -= 1;
+                    return;
+                }
+            }
+        };
+        let result = InputFile::from_path(path.clone());
+        // The receiver is dropped only when the app exits
+        let _ = sender.send((path, result));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::app::ffmpeg_manager::attributes::copy_xattrs;
+use crate::app::ffmpeg_manager::output::{get_free_path, get_partial_path};
+use std::fmt::{Display, Formatter};
+use std::fs::{copy, create_dir_all, remove_file, rename};
+use std::io::Error;
+use std::path::{Path, PathBuf};
+use strum_macros::EnumIter;
+
+/// Percent of the original size that the output has to save if not set
+pub const DEFAULT_MIN_SAVING: f64 = 10.0;
+
+#[derive(Debug, Default, EnumIter, PartialEq, Eq, Clone)]
+pub enum ReplaceOriginal {
+    #[default]
+    No,
+    Yes,
+    /// Outputs are encoded and checked, but originals are not touched
+    DryRun,
+}
+
+impl Display for ReplaceOriginal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplaceOriginal::No => f.write_str("no"),
+            ReplaceOriginal::Yes => f.write_str("yes"),
+            ReplaceOriginal::DryRun => f.write_str("dry run"),
+        }
+    }
+}
+
+/// Parses saving like "10%" or "10"
+pub fn parse_saving(saving: &str) -> Option<f64> {
+    saving
+        .trim()
+        .trim_end_matches('%')
+        .trim()
+        .parse()
+        .ok()
+        .filter(|saving: &f64| (0.0..100.0).contains(saving))
+}
+
+/// Renames the file, or copies it if the target is on another file system
+pub fn move_file(from: &Path, to: &Path) -> Result<(), Error> {
+    if rename(from, to).is_ok() {
+        return Ok(());
+    }
+    // The copy gets its name only when it is complete
+    let partial_path = get_partial_path(to);
+    if let Err(error) = copy(from, &partial_path)
+        .and_then(|_| copy_xattrs(from, &partial_path))
+        .and_then(|()| rename(&partial_path, to))
+    {
+        let _ = remove_file(&partial_path);
+        return Err(error);
+    }
+    remove_file(from)
+}
+
+/// Moves the file into the trash folder, a file with the same name there is kept.
+/// Returns the new path of the file.
+pub fn move_to_trash(path: &Path, trash_folder: &Path) -> Result<PathBuf, Error> {
+    create_dir_all(trash_folder)?;
+    let trash_path = get_free_path(&trash_folder.join(path.file_name().unwrap_or_default()));
+    move_file(path, &trash_path)?;
+    Ok(trash_path)
+}
+
+/// Renames the file to its name with the suffix added ("film.mkv.bak"), an existing backup
+/// is kept. Returns the new path of the file.
+pub fn move_to_backup(path: &Path, backup_suffix: &str) -> Result<PathBuf, Error> {
+    let mut backup_path = path.to_path_buf().into_os_string();
+    backup_path.push(backup_suffix);
+    let backup_path = get_free_path(Path::new(&backup_path));
+    move_file(path, &backup_path)?;
+    Ok(backup_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{read_to_string, remove_dir_all, write};
+
+    #[test]
+    fn parses_saving() {
+        assert_eq!(parse_saving("15%"), Some(15.0));
+        assert_eq!(parse_saving(" 7.5 % "), Some(7.5));
+        assert_eq!(parse_saving("100"), None);
+        assert_eq!(parse_saving("-1"), None);
+        assert_eq!(parse_saving("a lot"), None);
+    }
+
+    #[test]
+    fn keeps_existing_backups() {
+        let folder = std::env::temp_dir().join("film_compressor_replace_test");
+        let _ = remove_dir_all(&folder);
+        create_dir_all(&folder).unwrap();
+        let path = folder.join("film.mkv");
+        write(folder.join("film.mkv.bak"), "old").unwrap();
+        write(&path, "new").unwrap();
+        let backup_path = move_to_backup(&path, ".bak").unwrap();
+        write(&path, "newer").unwrap();
+        let trash_path = move_to_trash(&path, &folder.join("trash")).unwrap();
+        let contents = [
+            read_to_string(folder.join("film.mkv.bak")).unwrap(),
+            read_to_string(&backup_path).unwrap(),
+            read_to_string(&trash_path).unwrap(),
+        ];
+        let _ = remove_dir_all(&folder);
+        assert_eq!(backup_path, folder.join("film.mkv (1).bak"));
+        assert_eq!(trash_path, folder.join("trash/film.mkv"));
+        assert!(!path.exists());
+        assert_eq!(contents, ["old", "new", "newer"]);
+    }
+}
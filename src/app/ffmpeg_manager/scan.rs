@@ -0,0 +1,314 @@
+use crate::app::ffmpeg_manager::output::{is_output_folder_name, is_partial_path};
+use crate::app::ffmpeg_manager::xdg::get_config_dir;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+const VIDEO_EXTENSIONS: [&str; 15] = [
+    "mkv", "mp4", "m4v", "avi", "mov", "webm", "ts", "m2ts", "mts", "wmv", "flv", "mpg", "mpeg",
+    "vob", "ogv",
+];
+
+/// Rules of the folder scan. Files given directly are added without them.
+///
+/// Automatic output folders ("output", "output (n)") of the scanned folder and partial
+/// outputs are always skipped.
+#[derive(Debug, Clone)]
+pub struct ScanSettings {
+    /// Depth of subfolders, 0 scans only the given folder
+    pub max_depth: usize,
+    /// Globs of the files to add, empty adds all files
+    pub include: Vec<String>,
+    /// Globs of the files and folders to skip
+    pub exclude: Vec<String>,
+    /// Lowercase extensions of the files to add, empty adds all extensions
+    pub extensions: Vec<String>,
+    /// Files with "sample" in the name that are shorter are skipped, in seconds
+    pub sample_duration: Option<f64>,
+    pub follow_symlinks: bool,
+}
+
+impl Default for ScanSettings {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            extensions: VIDEO_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            sample_duration: Some(300.0),
+            follow_symlinks: false,
+        }
+    }
+}
+
+fn parse_strings(value: &Value) -> Option<Vec<String>> {
+    value.as_array().map(|values| {
+        values
+            .iter()
+            .filter_map(|value| value.as_str())
+            .map(|value| value.to_string())
+            .collect()
+    })
+}
+
+fn parse_extensions(extensions: &str) -> Vec<String> {
+    extensions
+        .split(',')
+        .map(|extension| {
+            extension
+                .trim()
+                .trim_start_matches('.')
+                .to_ascii_lowercase()
+        })
+        .filter(|extension| !extension.is_empty())
+        .collect()
+}
+
+impl ScanSettings {
+    /// Loads "scan" of $XDG_CONFIG_HOME/FilmCompressor/config.json.
+    /// Missing values are set to default.
+    pub fn load() -> Self {
+        let mut result = Self::default();
+        let mut path = get_config_dir();
+        path.push("config.json");
+        let Some(config) = read_to_string(path)
+            .ok()
+            .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+        else {
+            return result;
+        };
+        let scan = &config["scan"];
+        if let Some(max_depth) = scan["max_depth"].as_u64() {
+            result.max_depth = max_depth as usize;
+        }
+        if let Some(include) = parse_strings(&scan["include"]) {
+            result.include = include;
+        }
+        if let Some(exclude) = parse_strings(&scan["exclude"]) {
+            result.exclude = exclude;
+        }
+        if let Some(extensions) = parse_strings(&scan["extensions"]) {
+            result.extensions = parse_extensions(&extensions.join(","));
+        }
+        if scan["sample_duration"].is_null() && scan.get("sample_duration").is_some() {
+            result.sample_duration = None;
+        } else if let Some(sample_duration) = scan["sample_duration"].as_f64() {
+            result.sample_duration = Some(sample_duration);
+        }
+        if let Some(follow_symlinks) = scan["follow_symlinks"].as_bool() {
+            result.follow_symlinks = follow_symlinks;
+        }
+        result
+    }
+
+    /// Applies the command line option with its value. Returns false if the option is not
+    /// a scan option.
+    pub fn parse_arg(&mut self, arg: &str, args: &mut impl Iterator<Item = String>) -> bool {
+        match arg {
+            "--depth" => {
+                if let Some(max_depth) = args.next().and_then(|value| value.parse().ok()) {
+                    self.max_depth = max_depth;
+                }
+            }
+            "--include" => self.include.extend(args.next()),
+            "--exclude" => self.exclude.extend(args.next()),
+            "--ext" => {
+                self.extensions = parse_extensions(&args.next().unwrap_or_default());
+            }
+            "--sample-duration" => {
+                self.sample_duration = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&duration| duration > 0.0);
+            }
+            "--follow-symlinks" => self.follow_symlinks = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns files of the folder that pass the filters, sorted, and folders that can't
+    /// be read
+    pub fn scan(&self, folder: &Path) -> (Vec<PathBuf>, Vec<(PathBuf, Error)>) {
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        let mut visited = HashSet::new();
+        self.scan_folder(folder, folder, 0, &mut visited, &mut files, &mut errors);
+        files.sort();
+        (files, errors)
+    }
+
+    fn scan_folder(
+        &self,
+        root: &Path,
+        folder: &Path,
+        depth: usize,
+        visited: &mut HashSet<PathBuf>,
+        files: &mut Vec<PathBuf>,
+        errors: &mut Vec<(PathBuf, Error)>,
+    ) {
+        // Symlinked folders can make a loop
+        if let Ok(canonical) = folder.canonicalize()
+            && !visited.insert(canonical)
+        {
+            return;
+        }
+        let entries = match folder.read_dir() {
+            Ok(entries) => entries,
+            Err(error) => {
+                errors.push((folder.to_path_buf(), error));
+                return;
+            }
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_symlink() && !self.follow_symlinks {
+                continue;
+            }
+            let relative_path = path.strip_prefix(root).unwrap_or(&path);
+            if self
+                .exclude
+                .iter()
+                .any(|pattern| matches_path(pattern, relative_path))
+            {
+                continue;
+            }
+            if path.is_dir() {
+                // Automatic output folders are created in the scanned folder
+                if depth == 0 && is_output_folder_name(&entry.file_name().to_string_lossy()) {
+                    continue;
+                }
+                if depth < self.max_depth {
+                    self.scan_folder(root, &path, depth + 1, visited, files, errors);
+                }
+            } else if path.is_file() && self.is_included(relative_path) {
+                files.push(path);
+            }
+        }
+    }
+
+    fn is_included(&self, relative_path: &Path) -> bool {
+        if is_partial_path(relative_path) {
+            return false;
+        }
+        let extension = relative_path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        (self.extensions.is_empty() || self.extensions.contains(&extension))
+            && (self.include.is_empty()
+                || self
+                    .include
+                    .iter()
+                    .any(|pattern| matches_path(pattern, relative_path)))
+    }
+
+    /// Returns true for short clips like "Movie-sample.mkv" that are not worth compressing
+    pub fn is_sample(&self, path: &Path, duration: Option<f64>) -> bool {
+        let is_sample_name = path
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy().to_lowercase().contains("sample"));
+        match (self.sample_duration, duration) {
+            (Some(sample_duration), Some(duration)) => is_sample_name && duration < sample_duration,
+            _ => false,
+        }
+    }
+}
+
+/// Patterns with '/' are matched against the path relative to the scanned folder,
+/// other patterns against the file name
+fn matches_path(pattern: &str, relative_path: &Path) -> bool {
+    let text = if pattern.contains('/') {
+        relative_path.to_string_lossy()
+    } else {
+        relative_path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy())
+            .unwrap_or_default()
+    };
+    matches_glob(pattern.as_bytes(), text.as_bytes())
+}
+
+/// Glob with '*' (any characters) and '?' (one character), case-insensitive
+fn matches_glob(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| matches_glob(rest, &text[i..])),
+        Some((b'?', rest)) => !text.is_empty() && matches_glob(rest, &text[1..]),
+        Some((c, rest)) => text
+            .split_first()
+            .is_some_and(|(t, text)| c.eq_ignore_ascii_case(t) && matches_glob(rest, text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ffmpeg_manager::probe::ProbeError;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    #[test]
+    fn skips_outputs_of_earlier_runs() {
+        let folder = std::env::temp_dir().join("film_compressor_scan_test");
+        let _ = remove_dir_all(&folder);
+        for file in [
+            "film.mkv",
+            "film.partial.mkv",
+            "output/film.mkv",
+            "output (2)/film.mkv",
+            "extras/output/extra.mkv",
+        ] {
+            let path = folder.join(file);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, "").unwrap();
+        }
+
+        let (files, errors) = ScanSettings::default().scan(&folder);
+        let _ = remove_dir_all(&folder);
+        assert!(errors.is_empty());
+        assert_eq!(
+            files,
+            [
+                folder.join("extras/output/extra.mkv"),
+                folder.join("film.mkv")
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_globs() {
+        assert!(matches_glob(b"*.MKV", b"film.mkv"));
+        assert!(matches_glob(b"film?.mkv", b"film2.mkv"));
+        assert!(!matches_glob(b"film?.mkv", b"film.mkv"));
+        assert!(matches_glob(b"*sample*", b"film-Sample.mkv"));
+        assert!(!matches_glob(b"*.mkv", b"film.mp4"));
+    }
+
+    #[test]
+    fn matches_path_or_file_name() {
+        let path = Path::new("extras/behind the scenes.mkv");
+        assert!(matches_path("behind*", path));
+        assert!(matches_path("extras/*", path));
+        assert!(!matches_path("extras*", path));
+        assert!(!matches_path("other/*", path));
+    }
+
+    #[test]
+    fn detects_samples_by_name_and_duration() {
+        let scan_settings = ScanSettings::default();
+        let sample = Path::new("Movie/Movie-SAMPLE.mkv");
+        assert!(scan_settings.is_sample(sample, Some(30.0)));
+        assert!(!scan_settings.is_sample(sample, Some(3600.0)));
+        assert!(!scan_settings.is_sample(sample, None));
+        assert!(!scan_settings.is_sample(Path::new("Movie.mkv"), Some(30.0)));
+        assert_eq!(
+            ProbeError::Sample(30.0).get_reject_reason().to_string(),
+            "sample"
+        );
+    }
+}
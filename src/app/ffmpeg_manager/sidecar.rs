@@ -0,0 +1,110 @@
+use crate::app::ffmpeg_manager::language::to_iso639_2;
+use std::path::{Path, PathBuf};
+
+const SUBTITLE_EXTENSIONS: [&str; 6] = ["srt", "ass", "ssa", "sub", "vtt", "sup"];
+const AUDIO_EXTENSIONS: [&str; 10] = [
+    "mka", "ac3", "eac3", "dts", "aac", "flac", "opus", "mp3", "m4a", "wav",
+];
+
+fn has_sidecar_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| extension.to_ascii_lowercase())
+        .is_some_and(|extension| {
+            SUBTITLE_EXTENSIONS.contains(&extension.as_str())
+                || AUDIO_EXTENSIONS.contains(&extension.as_str())
+        })
+}
+
+/// Returns part of the sidecar file name after the main file stem: "rus.srt" for
+/// "Movie.rus.srt" and "Movie.mkv", or None if the file is not a sidecar of the main file
+pub fn get_sidecar_suffix(sidecar: &Path, main: &Path) -> Option<String> {
+    if sidecar == main || !has_sidecar_extension(sidecar) || sidecar.parent() != main.parent() {
+        return None;
+    }
+    let stem = main.file_stem()?.to_str()?;
+    let file_name = sidecar.file_name()?.to_str()?;
+    file_name
+        .strip_prefix(stem)?
+        .strip_prefix('.')
+        .map(|suffix| suffix.to_string())
+}
+
+/// Returns sidecar files that lie next to the main file
+pub fn find_sidecars(main: &Path) -> Vec<PathBuf> {
+    let Some(Ok(entries)) = main.parent().map(|parent| {
+        if parent.as_os_str().is_empty() {
+            Path::new(".").read_dir()
+        } else {
+            parent.read_dir()
+        }
+    }) else {
+        return Vec::new();
+    };
+    let mut result: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| match main.parent() {
+            Some(parent) => parent.join(entry.file_name()),
+            None => entry.path(),
+        })
+        .filter(|path| path.is_file() && get_sidecar_suffix(path, main).is_some())
+        .collect();
+    result.sort();
+    result
+}
+
+/// Returns language and title from the sidecar suffix: "rus.forced.srt" -> (rus, forced)
+pub fn parse_sidecar_suffix(suffix: &str) -> (Option<String>, Option<String>) {
+    let mut parts: Vec<&str> = suffix.split('.').collect();
+    parts.pop();
+    let mut language = None;
+    let mut title = Vec::new();
+    for part in parts {
+        match to_iso639_2(part) {
+            Some(code) if language.is_none() => language = Some(code.to_string()),
+            _ => title.push(part),
+        }
+    }
+    let title = Some(title.join(" ")).filter(|title| !title.is_empty());
+    (language, title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_sidecar_suffix() {
+        let main = Path::new("films/Movie.mkv");
+        let suffix = |sidecar: &str| get_sidecar_suffix(Path::new(sidecar), main);
+        assert_eq!(suffix("films/Movie.rus.srt").as_deref(), Some("rus.srt"));
+        assert_eq!(suffix("films/Movie.srt").as_deref(), Some("srt"));
+        assert_eq!(
+            suffix("films/Movie.eng.Commentary.AC3").as_deref(),
+            Some("eng.Commentary.AC3")
+        );
+        assert_eq!(suffix("films/Movie.mkv"), None);
+        assert_eq!(suffix("films/Movie.nfo"), None);
+        assert_eq!(suffix("films/Movie 2.srt"), None);
+        assert_eq!(suffix("films/Movie2.srt"), None);
+        assert_eq!(suffix("other/Movie.srt"), None);
+    }
+
+    #[test]
+    fn parses_sidecar_suffix() {
+        let parse = parse_sidecar_suffix;
+        assert_eq!(parse("srt"), (None, None));
+        assert_eq!(parse("ru.srt"), (Some(String::from("rus")), None));
+        assert_eq!(
+            parse("rus.forced.srt"),
+            (Some(String::from("rus")), Some(String::from("forced")))
+        );
+        assert_eq!(
+            parse("Director.Commentary.eng.ac3"),
+            (
+                Some(String::from("eng")),
+                Some(String::from("Director Commentary"))
+            )
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use crate::app::ffmpeg_manager::ffmpeg_manager::{InputFile, StreamType};
+use std::fmt::{Display, Formatter};
+
+/// Rules of the files that are not worth compressing, checked against probe data.
+///
+/// Text form: "codecs av1,hevc; bpp 0.05; size 200"
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SkipRules {
+    /// Files with the first video stream in one of these codecs are skipped
+    pub codecs: Vec<String>,
+    /// Files with video bits per pixel below this are skipped
+    pub min_bits_per_pixel: Option<f64>,
+    /// Files smaller than this are skipped, in MB
+    pub min_size: Option<f64>,
+}
+
+impl SkipRules {
+    pub fn parse(rules: &str) -> Self {
+        let mut result = Self::default();
+        for rule in rules.split(';') {
+            let rule = rule.trim().to_ascii_lowercase();
+            if let Some(codecs) = rule.strip_prefix("codecs ").or(rule.strip_prefix("codec ")) {
+                result.codecs = codecs
+                    .split([',', ' '])
+                    .filter(|codec| !codec.is_empty())
+                    .map(|codec| codec.to_string())
+                    .collect();
+            } else if let Some(bits_per_pixel) = rule.strip_prefix("bpp ") {
+                result.min_bits_per_pixel = bits_per_pixel.trim().parse().ok();
+            } else if let Some(size) = rule.strip_prefix("size ") {
+                result.min_size = size.trim().trim_end_matches("mb").trim().parse().ok();
+            }
+        }
+        result
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns the reason the file is skipped or None if it should be compressed
+    pub fn get_skip_reason(&self, input_file: &InputFile) -> Option<String> {
+        if let Some(min_size) = self.min_size
+            && let Some(size) = input_file.format.size
+            && (size as f64) < min_size * 1_000_000.0
+        {
+            return Some(format!("smaller than {} MB", min_size));
+        }
+        let (codec_name, video_data) =
+            input_file
+                .sources
+                .iter()
+                .find_map(|source| match &source.stream_type {
+                    StreamType::Video(video_data) => Some((&source.codec_name, video_data)),
+                    _ => None,
+                })?;
+        if self.codecs.contains(codec_name) {
+            return Some(format!("already {}", codec_name));
+        }
+        if let Some(min_bits_per_pixel) = self.min_bits_per_pixel {
+            // Without the stream bitrate the overall one is used, audio is left out of it
+            let bit_rate = video_data.bit_rate.or_else(|| {
+                let audio_bit_rate: u64 = input_file
+                    .sources
+                    .iter()
+                    .filter_map(|source| match &source.stream_type {
+                        StreamType::Audio(audio_data) => audio_data.bit_rate,
+                        _ => None,
+                    })
+                    .sum();
+                input_file
+                    .format
+                    .bit_rate
+                    .map(|bit_rate| bit_rate.saturating_sub(audio_bit_rate))
+            })?;
+            let frame_rate = video_data.avg_frame_rate.or(video_data.r_frame_rate)?;
+            let (width, height) = video_data.resolution;
+            let pixels_per_second = (width * height) as f64 * frame_rate;
+            if pixels_per_second > 0.0 {
+                let bits_per_pixel = bit_rate as f64 / pixels_per_second;
+                if bits_per_pixel < min_bits_per_pixel {
+                    return Some(format!("{:.3} bits per pixel", bits_per_pixel));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Display for SkipRules {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut rules = Vec::new();
+        if !self.codecs.is_empty() {
+            rules.push(format!("codecs {}", self.codecs.join(",")));
+        }
+        if let Some(min_bits_per_pixel) = self.min_bits_per_pixel {
+            rules.push(format!("bpp {}", min_bits_per_pixel));
+        }
+        if let Some(min_size) = self.min_size {
+            rules.push(format!("size {}", min_size));
+        }
+        f.write_str(&rules.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ffmpeg_manager::ffmpeg_manager::StreamOverride;
+    use crate::app::ffmpeg_manager::probe::parse_probe;
+    use serde_json::{Value, json};
+    use std::path::PathBuf;
+
+    fn get_input_file(json_data: Value) -> InputFile {
+        let probe_data = parse_probe(&json_data).unwrap();
+        InputFile {
+            path: PathBuf::from("film.mkv"),
+            overrides: vec![StreamOverride::default(); probe_data.streams.len()],
+            sources: probe_data.streams,
+            chapters: probe_data.chapters,
+            format: probe_data.format,
+            sidecars: Vec::new(),
+            probe_errors: probe_data.errors,
+            skip_override: None,
+        }
+    }
+
+    /// 1080p at 24 fps with 2 Mbit/s of video, 0.040 bits per pixel
+    fn get_film(codec_name: &str, video_bit_rate: Option<&str>) -> InputFile {
+        get_input_file(json!({
+            "streams": [
+                {
+                    "index": 0, "codec_type": "video", "codec_name": codec_name,
+                    "width": 1920, "height": 1080, "avg_frame_rate": "24/1",
+                    "bit_rate": video_bit_rate,
+                },
+                {"index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2, "bit_rate": "192000"},
+            ],
+            "format": {"size": "500000000", "bit_rate": "2192000"},
+        }))
+    }
+
+    #[test]
+    fn parses_rules() {
+        let skip_rules = SkipRules::parse("Codecs av1, hevc; bpp 0.05; size 200 MB");
+        assert_eq!(
+            skip_rules,
+            SkipRules {
+                codecs: vec![String::from("av1"), String::from("hevc")],
+                min_bits_per_pixel: Some(0.05),
+                min_size: Some(200.0),
+            }
+        );
+        assert_eq!(SkipRules::parse(&skip_rules.to_string()), skip_rules);
+        assert!(SkipRules::parse("").is_empty());
+    }
+
+    #[test]
+    fn skips_by_size_codec_and_bits_per_pixel() {
+        let film = get_film("h264", Some("2000000"));
+        let reason = |rules: &str| SkipRules::parse(rules).get_skip_reason(&film);
+        assert_eq!(reason("size 600").as_deref(), Some("smaller than 600 MB"));
+        assert_eq!(reason("size 400"), None);
+        assert_eq!(reason("codecs hevc,h264").as_deref(), Some("already h264"));
+        assert_eq!(reason("codecs hevc"), None);
+        assert_eq!(reason("bpp 0.05").as_deref(), Some("0.040 bits per pixel"));
+        assert_eq!(reason("bpp 0.03"), None);
+    }
+
+    #[test]
+    fn estimates_video_bit_rate_without_audio() {
+        let film = get_film("h264", None);
+        let skip_rules = SkipRules::parse("bpp 0.05");
+        assert_eq!(
+            skip_rules.get_skip_reason(&film).as_deref(),
+            Some("0.040 bits per pixel")
+        );
+    }
+}
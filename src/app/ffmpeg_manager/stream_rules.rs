@@ -0,0 +1,247 @@
+use crate::app::ffmpeg_manager::ffmpeg_manager::{FfmpegStreamSettings, StreamType};
+use crate::app::ffmpeg_manager::language::to_iso639_2;
+use std::fmt::{Display, Formatter};
+
+/// Language-based selection of audio and subtitle streams.
+///
+/// Text form: "audio rus,eng,jpn; default audio rus; subtitles eng,rus; forced subtitles default"
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StreamRules {
+    pub audio_languages: Vec<String>,
+    pub default_audio: Option<String>,
+    pub subtitle_languages: Vec<String>,
+    pub default_subtitle: Option<String>,
+    pub forced_subtitles_default: bool,
+}
+
+impl StreamRules {
+    /// Returns an error naming the first rule that is not understood
+    pub fn parse(rules: &str) -> Result<Self, String> {
+        let mut result = Self::default();
+        for rule in rules.split(';') {
+            let rule = rule.trim().to_ascii_lowercase();
+            if rule.is_empty() {
+                continue;
+            }
+            let words: Vec<&str> = rule
+                .split([',', ' '])
+                .filter(|word| !word.is_empty())
+                .collect();
+            match words.as_slice() {
+                ["forced", "subtitle" | "subtitles", "default"] => {
+                    result.forced_subtitles_default = true
+                }
+                ["default", "audio", language] => {
+                    result.default_audio = Some(parse_language(language, &rule)?)
+                }
+                ["default", "subtitle" | "subtitles", language] => {
+                    result.default_subtitle = Some(parse_language(language, &rule)?)
+                }
+                ["keep", "audio", "in", languages @ ..]
+                | ["keep", "audio", languages @ ..]
+                | ["audio", languages @ ..] => {
+                    result.audio_languages = parse_languages(languages, &rule)?
+                }
+                ["keep", "subtitle" | "subtitles", "in", languages @ ..]
+                | ["keep", "subtitle" | "subtitles", languages @ ..]
+                | ["subtitle" | "subtitles", languages @ ..] => {
+                    result.subtitle_languages = parse_languages(languages, &rule)?
+                }
+                _ => return Err(format!("Unknown stream rule \"{}\"", rule)),
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sets enabled and default of the stream. Streams without language are kept, but never
+    /// become default by language.
+    pub fn apply(&self, stream_settings: &mut FfmpegStreamSettings) {
+        let (language, languages, default_language, forced) =
+            match &stream_settings.stream.stream_type {
+                StreamType::Audio(audio_data) => (
+                    &audio_data.language,
+                    &self.audio_languages,
+                    &self.default_audio,
+                    false,
+                ),
+                StreamType::Subtitle(subtitle_data) => (
+                    &subtitle_data.language,
+                    &self.subtitle_languages,
+                    &self.default_subtitle,
+                    self.forced_subtitles_default
+                        && (stream_settings.stream.disposition.forced
+                            || subtitle_data
+                                .title
+                                .as_ref()
+                                .is_some_and(|title| title.to_lowercase().contains("forced"))),
+                ),
+                _ => return,
+            };
+        let language = language
+            .as_deref()
+            .and_then(to_iso639_2)
+            .filter(|&language| language != "und");
+        if let Some(language) = language
+            && !languages.is_empty()
+        {
+            stream_settings.enabled = languages.iter().any(|l| l == language);
+        }
+        if default_language.is_some() || forced {
+            stream_settings.disposition.default =
+                forced || (language.is_some() && default_language.as_deref() == language);
+        }
+        if !stream_settings.enabled {
+            stream_settings.disposition.default = false;
+        }
+    }
+}
+
+fn parse_language(language: &str, rule: &str) -> Result<String, String> {
+    to_iso639_2(language)
+        .map(|language| language.to_string())
+        .ok_or(format!(
+            "Unknown language \"{}\" in rule \"{}\"",
+            language, rule
+        ))
+}
+
+fn parse_languages(languages: &[&str], rule: &str) -> Result<Vec<String>, String> {
+    if languages.is_empty() {
+        return Err(format!("No languages in rule \"{}\"", rule));
+    }
+    languages
+        .iter()
+        .map(|language| parse_language(language, rule))
+        .collect()
+}
+
+impl Display for StreamRules {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut rules = Vec::new();
+        if !self.audio_languages.is_empty() {
+            rules.push(format!("audio {}", self.audio_languages.join(",")));
+        }
+        if let Some(language) = &self.default_audio {
+            rules.push(format!("default audio {}", language));
+        }
+        if !self.subtitle_languages.is_empty() {
+            rules.push(format!("subtitles {}", self.subtitle_languages.join(",")));
+        }
+        if let Some(language) = &self.default_subtitle {
+            rules.push(format!("default subtitles {}", language));
+        }
+        if self.forced_subtitles_default {
+            rules.push(String::from("forced subtitles default"));
+        }
+        f.write_str(&rules.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ffmpeg_manager::disposition::Disposition;
+    use crate::app::ffmpeg_manager::ffmpeg_manager::{
+        AudioData, FfmpegStreamFiles, Stream, SubtitleData,
+    };
+
+    fn get_stream_settings(stream_type: StreamType) -> FfmpegStreamSettings {
+        FfmpegStreamSettings {
+            stream: Stream {
+                index: 0,
+                stream_type,
+                codec_name: String::new(),
+                disposition: Disposition::default(),
+                origin: None,
+            },
+            files: FfmpegStreamFiles::All,
+            enabled: true,
+            disposition: Disposition::default(),
+            title: None,
+            language: None,
+        }
+    }
+
+    fn get_audio(language: Option<&str>) -> FfmpegStreamSettings {
+        get_stream_settings(StreamType::Audio(AudioData {
+            title: None,
+            channels: 2,
+            language: language.map(|language| language.to_string()),
+            channel_layout: None,
+            sample_rate: None,
+            bit_rate: None,
+            bits_per_raw_sample: None,
+        }))
+    }
+
+    #[test]
+    fn parses_rules() {
+        let rules = StreamRules::parse(
+            "keep audio in rus, eng, jpn; default audio ru; Keep Subtitles eng,rus; \
+             forced subtitles default;",
+        )
+        .unwrap();
+        assert_eq!(
+            rules,
+            StreamRules {
+                audio_languages: vec![
+                    String::from("rus"),
+                    String::from("eng"),
+                    String::from("jpn")
+                ],
+                default_audio: Some(String::from("rus")),
+                subtitle_languages: vec![String::from("eng"), String::from("rus")],
+                default_subtitle: None,
+                forced_subtitles_default: true,
+            }
+        );
+        assert_eq!(StreamRules::parse(&rules.to_string()), Ok(rules));
+        assert_eq!(StreamRules::parse(" "), Ok(StreamRules::default()));
+    }
+
+    #[test]
+    fn rejects_unknown_rules_and_languages() {
+        assert!(StreamRules::parse("video eng").is_err());
+        assert!(StreamRules::parse("audio eng, xyz").is_err());
+        assert!(StreamRules::parse("keep audio in").is_err());
+        assert!(StreamRules::parse("default audio rus eng").is_err());
+        assert!(StreamRules::parse("audio in eng").is_err());
+    }
+
+    #[test]
+    fn applies_rules_by_language() {
+        let rules = StreamRules::parse("audio rus,eng; default audio eng").unwrap();
+        let mut russian = get_audio(Some("ru"));
+        let mut english = get_audio(Some("eng"));
+        let mut japanese = get_audio(Some("jpn"));
+        let mut unknown = get_audio(None);
+        for stream_settings in [&mut russian, &mut english, &mut japanese, &mut unknown] {
+            rules.apply(stream_settings);
+        }
+        assert!(russian.enabled && !russian.disposition.default);
+        assert!(english.enabled && english.disposition.default);
+        assert!(!japanese.enabled && !japanese.disposition.default);
+        assert!(unknown.enabled && !unknown.disposition.default);
+    }
+
+    #[test]
+    fn makes_forced_subtitles_default() {
+        let rules = StreamRules::parse("subtitles eng; forced subtitles default").unwrap();
+        let mut forced = get_stream_settings(StreamType::Subtitle(SubtitleData {
+            title: Some(String::from("English (Forced)")),
+            language: Some(String::from("eng")),
+        }));
+        let mut full = get_stream_settings(StreamType::Subtitle(SubtitleData {
+            title: None,
+            language: Some(String::from("eng")),
+        }));
+        rules.apply(&mut forced);
+        rules.apply(&mut full);
+        assert!(forced.enabled && forced.disposition.default);
+        assert!(full.enabled && !full.disposition.default);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::app::ffmpeg_manager::probe::ProbeError;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+/// Difference between durations of the input and a complete output, in seconds
+pub const DURATION_TOLERANCE: f64 = 1.0;
+
+/// Seconds that are decoded at the start and at the end of the output
+const DECODE_DURATION: f64 = 5.0;
+
+/// Names of the stream types by `StreamType::to_index`
+const STREAM_TYPE_NAMES: [&str; 5] = ["video", "audio", "subtitle", "attachment", "data"];
+
+/// Reason the written output doesn't match its input
+#[derive(Debug)]
+pub enum VerifyError {
+    Probe(ProbeError),
+    Duration {
+        expected: f64,
+        actual: Option<f64>,
+    },
+    /// Number of streams of the type after the stream selection
+    StreamCount {
+        stream_type: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// ffmpeg failed to decode the start or the end of the file
+    Decode {
+        at_end: bool,
+        message: String,
+    },
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyError::Probe(error) => write!(f, "{}", error),
+            VerifyError::Duration {
+                expected,
+                actual: Some(actual),
+            } => write!(
+                f,
+                "duration is {:.1} s instead of {:.1} s",
+                actual, expected
+            ),
+            VerifyError::Duration {
+                expected,
+                actual: None,
+            } => write!(f, "no duration, expected {:.1} s", expected),
+            VerifyError::StreamCount {
+                stream_type,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} {} streams instead of {}",
+                actual, STREAM_TYPE_NAMES[*stream_type], expected
+            ),
+            VerifyError::Decode { at_end, message } => write!(
+                f,
+                "{} can't be decoded: {}",
+                if *at_end { "end" } else { "start" },
+                message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Decodes the first or the last seconds of the file, any decoding error fails the check
+pub fn check_decodable(path: &Path, at_end: bool) -> Result<(), VerifyError> {
+    let mut command = Command::new("ffmpeg");
+    command.arg("-v").arg("error");
+    if at_end {
+        command.arg("-sseof").arg(format!("-{}", DECODE_DURATION));
+    } else {
+        command.arg("-t").arg(DECODE_DURATION.to_string());
+    }
+    let output = command
+        .arg("-i")
+        .arg(path)
+        .arg("-f")
+        .arg("null")
+        .arg("-")
+        .output()
+        .map_err(|error| VerifyError::Decode {
+            at_end,
+            message: match error.kind() {
+                ErrorKind::NotFound => String::from("ffmpeg is not found"),
+                _ => error.to_string(),
+            },
+        })?;
+    let message = String::from_utf8_lossy(&output.stderr);
+    let message = message.lines().last().unwrap_or_default().trim();
+    if output.status.success() && message.is_empty() {
+        return Ok(());
+    }
+    Err(VerifyError::Decode {
+        at_end,
+        message: if message.is_empty() {
+            String::from("ffmpeg exited with non-zero status code")
+        } else {
+            message.to_string()
+        },
+    })
+}
@@ -0,0 +1,27 @@
+use std::env;
+use std::path::PathBuf;
+
+fn get_base_dir(variable: &str, fallback: &str) -> PathBuf {
+    match env::var_os(variable) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let mut result = PathBuf::from(env::var_os("HOME").unwrap_or_default());
+            result.push(fallback);
+            result
+        }
+    }
+}
+
+/// Returns $XDG_CONFIG_HOME/FilmCompressor
+pub fn get_config_dir() -> PathBuf {
+    let mut result = get_base_dir("XDG_CONFIG_HOME", ".config");
+    result.push("FilmCompressor");
+    result
+}
+
+/// Returns $XDG_CACHE_HOME/FilmCompressor
+pub fn get_cache_dir() -> PathBuf {
+    let mut result = get_base_dir("XDG_CACHE_HOME", ".cache");
+    result.push("FilmCompressor");
+    result
+}
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 mod app;
 mod ffmpeg_manager;
 mod hotkey;